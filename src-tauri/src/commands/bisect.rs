@@ -0,0 +1,73 @@
+//! Bisect commands
+//!
+//! Commands for finding the commit that introduced a bug.
+
+use crate::git_ops::{GitRepository, BisectStatus, BisectRunResult};
+use super::response::ApiResponse;
+
+/// Start bisecting between a bad commit and one or more good commits
+#[tauri::command]
+pub fn bisect_start(repo_path: String, bad: String, good: Vec<String>) -> ApiResponse<BisectStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.bisect_start(&bad, &good) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Mark a commit (HEAD by default) as good, bad or skip and check out the next one
+#[tauri::command]
+pub fn bisect_mark(repo_path: String, verdict: String, commit: Option<String>) -> ApiResponse<BisectStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.bisect_mark(&verdict, commit.as_deref()) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Get the current bisect state
+#[tauri::command]
+pub fn get_bisect_status(repo_path: String) -> ApiResponse<BisectStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.bisect_status() {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// End bisecting and return to the original branch
+#[tauri::command]
+pub fn bisect_reset(repo_path: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.bisect_reset() {
+            Ok(_) => ApiResponse::success("Bisect reset successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Bisect automatically using a command's exit code (异步执行，不阻塞主线程)
+#[tauri::command]
+pub async fn bisect_run(repo_path: String, command: String) -> ApiResponse<BisectRunResult> {
+    let run_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.bisect_run(&command) {
+                Ok(result) => ApiResponse::success(result),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match run_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
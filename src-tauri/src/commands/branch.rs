@@ -33,7 +33,7 @@ pub fn create_branch(repo_path: String, branch_name: String) -> ApiResponse<Stri
 #[tauri::command]
 pub fn checkout_branch(repo_path: String, branch_name: String) -> ApiResponse<String> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.checkout_branch(&branch_name) {
+        Ok(mut repo) => match repo.record_operation("checkout", &format!("Checkout {}", branch_name), |r| r.checkout_branch(&branch_name)) {
             Ok(_) => ApiResponse::success("Branch checked out successfully".to_string()),
             Err(e) => ApiResponse::error(e.to_string()),
         },
@@ -45,7 +45,7 @@ pub fn checkout_branch(repo_path: String, branch_name: String) -> ApiResponse<St
 #[tauri::command]
 pub fn delete_branch(repo_path: String, branch_name: String) -> ApiResponse<String> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.delete_branch(&branch_name) {
+        Ok(mut repo) => match repo.record_operation("branch-delete", &format!("Delete branch {}", branch_name), |r| r.delete_branch(&branch_name)) {
             Ok(_) => ApiResponse::success("Branch deleted successfully".to_string()),
             Err(e) => ApiResponse::error(e.to_string()),
         },
@@ -0,0 +1,34 @@
+//! Credential helper commands
+//!
+//! Commands for inspecting git credential helpers and installing CaoGit's keychain helper.
+
+use crate::git_ops::{CredentialHelperInfo, GitRepository};
+use super::response::ApiResponse;
+
+/// Get the credential helpers that apply to a remote (defaults to origin)
+#[tauri::command]
+pub fn get_credential_helpers(repo_path: String, remote_name: Option<String>) -> ApiResponse<CredentialHelperInfo> {
+    let remote_name = remote_name.unwrap_or_else(|| "origin".to_string());
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => {
+            let url = match repo.get_remote_url(&remote_name) {
+                Ok(url) => url,
+                Err(e) => return ApiResponse::error(e.to_string()),
+            };
+            match repo.get_credential_helpers(&url) {
+                Ok(info) => ApiResponse::success(info),
+                Err(e) => ApiResponse::error(e.to_string()),
+            }
+        }
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Register or unregister CaoGit as a git credential helper, so the git command shares the app's keychain credentials
+#[tauri::command]
+pub fn set_keychain_credential_helper(enabled: bool) -> ApiResponse<()> {
+    match crate::git_ops::set_keychain_helper(enabled) {
+        Ok(_) => ApiResponse::success(()),
+        Err(e) => ApiResponse::error(format!("Failed to update credential.helper: {}", e)),
+    }
+}
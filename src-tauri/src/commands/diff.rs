@@ -2,14 +2,14 @@
 //!
 //! Commands for diff and blame operations.
 
-use crate::git_ops::{GitRepository, DiffResult, BlameLine};
+use crate::git_ops::{GitRepository, DiffResult, DiffSettings, BlameLine};
 use super::response::ApiResponse;
 
 /// Get file diff
 #[tauri::command]
-pub fn get_file_diff(repo_path: String, file_path: String, staged: bool) -> ApiResponse<DiffResult> {
+pub fn get_file_diff(repo_path: String, file_path: String, staged: bool, options: Option<DiffSettings>) -> ApiResponse<DiffResult> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.get_file_diff(&file_path, staged) {
+        Ok(repo) => match repo.get_file_diff(&file_path, staged, &options.unwrap_or_default()) {
             Ok(diff) => ApiResponse::success(diff),
             Err(e) => ApiResponse::error(e.to_string()),
         },
@@ -17,6 +17,24 @@ pub fn get_file_diff(repo_path: String, file_path: String, staged: bool) -> ApiR
     }
 }
 
+/// Diff two revisions (revspecs or the INDEX / WORKDIR tokens), optionally limited to paths
+#[tauri::command]
+pub fn diff_revisions(
+    repo_path: String,
+    from: String,
+    to: String,
+    paths: Option<Vec<String>>,
+    options: Option<DiffSettings>,
+) -> ApiResponse<Vec<DiffResult>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.diff_revisions(&from, &to, &paths.unwrap_or_default(), &options.unwrap_or_default()) {
+            Ok(diffs) => ApiResponse::success(diffs),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
 /// Get file blame
 #[tauri::command]
 pub fn get_file_blame(repo_path: String, file_path: String) -> ApiResponse<Vec<BlameLine>> {
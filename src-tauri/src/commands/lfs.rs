@@ -0,0 +1,85 @@
+//! Git LFS commands
+//!
+//! Commands for inspecting LFS files, downloading objects and managing locks.
+
+use crate::git_ops::{GitRepository, LfsInfo, LfsFileInfo, LfsLock};
+use super::response::ApiResponse;
+
+/// Get tracked LFS patterns and whether git-lfs is installed
+#[tauri::command]
+pub fn get_lfs_info(repo_path: String) -> ApiResponse<LfsInfo> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_lfs_info() {
+            Ok(info) => ApiResponse::success(info),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// List LFS files with their pointer/smudged state
+#[tauri::command]
+pub fn get_lfs_files(repo_path: String) -> ApiResponse<Vec<LfsFileInfo>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_lfs_files() {
+            Ok(files) => ApiResponse::success(files),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Download LFS objects and check them out (异步执行，不阻塞主线程)
+#[tauri::command]
+pub async fn lfs_pull(repo_path: String, include: Option<Vec<String>>) -> ApiResponse<String> {
+    let pull_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.lfs_pull(&include.unwrap_or_default()) {
+                Ok(output) => ApiResponse::success(output),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match pull_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// List LFS locks
+#[tauri::command]
+pub fn lfs_locks(repo_path: String) -> ApiResponse<Vec<LfsLock>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.lfs_locks() {
+            Ok(locks) => ApiResponse::success(locks),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Lock a file on the LFS server
+#[tauri::command]
+pub fn lfs_lock(repo_path: String, path: String) -> ApiResponse<LfsLock> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.lfs_lock(&path) {
+            Ok(lock) => ApiResponse::success(lock),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Unlock a file on the LFS server
+#[tauri::command]
+pub fn lfs_unlock(repo_path: String, path: String, force: Option<bool>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.lfs_unlock(&path, force.unwrap_or(false)) {
+            Ok(_) => ApiResponse::success("File unlocked successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
@@ -1,19 +1,28 @@
 //! Merge commands
 //!
-//! Commands for merge, cherry-pick, and conflict resolution operations.
+//! Commands for merge, cherry-pick, revert, and conflict resolution operations.
 
-use crate::git_ops::{GitRepository, ConflictInfo};
+use tauri::Window;
+use crate::git_ops::{GitRepository, ConflictInfo, HookOptions};
 use super::response::ApiResponse;
 
 /// Merge a branch
 #[tauri::command]
-pub fn merge_branch(repo_path: String, branch_name: String) -> ApiResponse<String> {
-    match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.merge(&branch_name) {
-            Ok(msg) => ApiResponse::success(msg),
+pub async fn merge_branch(window: Window, repo_path: String, branch_name: String) -> ApiResponse<String> {
+    let hooks = HookOptions { no_verify: false, window: Some(window) };
+    let merge_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.with_hooks(hooks).record_operation("merge", &format!("Merge {}", branch_name), |r| r.merge(&branch_name)) {
+                Ok(msg) => ApiResponse::success(msg),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
             Err(e) => ApiResponse::error(e.to_string()),
-        },
-        Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match merge_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
     }
 }
 
@@ -21,7 +30,7 @@ pub fn merge_branch(repo_path: String, branch_name: String) -> ApiResponse<Strin
 #[tauri::command]
 pub fn cherry_pick(repo_path: String, commit_hash: String) -> ApiResponse<String> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.cherry_pick(&commit_hash) {
+        Ok(mut repo) => match repo.record_operation("cherry-pick", &format!("Cherry-pick {}", commit_hash), |r| r.cherry_pick(&commit_hash)) {
             Ok(msg) => ApiResponse::success(msg),
             Err(e) => ApiResponse::error(e.to_string()),
         },
@@ -33,7 +42,43 @@ pub fn cherry_pick(repo_path: String, commit_hash: String) -> ApiResponse<String
 #[tauri::command]
 pub fn cherry_pick_batch(repo_path: String, commit_hashes: Vec<String>) -> ApiResponse<Vec<String>> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.cherry_pick_batch(commit_hashes) {
+        Ok(mut repo) => match repo.record_operation(
+            "cherry-pick",
+            &format!("Cherry-pick {} commits", commit_hashes.len()),
+            |r| r.cherry_pick_batch(commit_hashes),
+        ) {
+            Ok(results) => ApiResponse::success(results),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Revert a single commit
+#[tauri::command]
+pub fn revert_commit(repo_path: String, commit_hash: String, mainline: Option<u32>, no_commit: Option<bool>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation(
+            "revert",
+            &format!("Revert {}", commit_hash),
+            |r| r.revert(&commit_hash, mainline, no_commit.unwrap_or(false)),
+        ) {
+            Ok(msg) => ApiResponse::success(msg),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Revert multiple commits
+#[tauri::command]
+pub fn revert_batch(repo_path: String, commit_hashes: Vec<String>, mainline: Option<u32>, no_commit: Option<bool>) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation(
+            "revert",
+            &format!("Revert {} commits", commit_hashes.len()),
+            |r| r.revert_batch(commit_hashes, mainline, no_commit.unwrap_or(false)),
+        ) {
             Ok(results) => ApiResponse::success(results),
             Err(e) => ApiResponse::error(e.to_string()),
         },
@@ -41,6 +86,30 @@ pub fn cherry_pick_batch(repo_path: String, commit_hashes: Vec<String>) -> ApiRe
     }
 }
 
+/// Commit an in-progress revert after resolving conflicts
+#[tauri::command]
+pub fn revert_continue(repo_path: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("revert", "Revert", |r| r.revert_continue()) {
+            Ok(msg) => ApiResponse::success(msg),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Abort an in-progress revert
+#[tauri::command]
+pub fn revert_abort(repo_path: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.revert_abort() {
+            Ok(_) => ApiResponse::success("Revert aborted successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
 /// Get merge conflicts
 #[tauri::command]
 pub fn get_conflicts(repo_path: String) -> ApiResponse<Vec<ConflictInfo>> {
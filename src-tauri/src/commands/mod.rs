@@ -11,9 +11,19 @@ mod diff;
 mod stash;
 mod tag;
 mod merge;
+mod rebase;
+mod patch;
+mod reset;
+mod undo;
+mod bisect;
+mod worktree;
+mod submodule;
+mod lfs;
+mod sparse;
 mod utils;
 mod ai;
 mod keychain;
+mod credential;
 
 // Re-export all commands
 pub use repository::*;
@@ -24,6 +34,16 @@ pub use diff::*;
 pub use stash::*;
 pub use tag::*;
 pub use merge::*;
+pub use rebase::*;
+pub use patch::*;
+pub use reset::*;
+pub use undo::*;
+pub use bisect::*;
+pub use worktree::*;
+pub use submodule::*;
+pub use lfs::*;
+pub use sparse::*;
 pub use utils::*;
 pub use ai::*;
 pub use keychain::*;
+pub use credential::*;
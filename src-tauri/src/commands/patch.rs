@@ -0,0 +1,46 @@
+//! Patch commands
+//!
+//! Commands for exporting commits as patches and applying patches
+//! (format-patch, apply and am).
+
+use crate::git_ops::{GitRepository, PatchFile};
+use super::response::ApiResponse;
+
+/// Export the commits in `from..to` as mbox patches, optionally writing them to a directory
+#[tauri::command]
+pub fn format_patch(repo_path: String, from: String, to: String, output_dir: Option<String>) -> ApiResponse<Vec<PatchFile>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.format_patch(&from, &to, output_dir.as_deref()) {
+            Ok(patches) => ApiResponse::success(patches),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Apply a patch file to the working directory and/or index, or only check that it applies
+#[tauri::command]
+pub fn apply_patch(repo_path: String, patch_path: String, location: Option<String>, check_only: Option<bool>) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => {
+            let location = location.unwrap_or_else(|| "workdir".to_string());
+            match repo.apply_patch(&patch_path, &location, check_only.unwrap_or(false)) {
+                Ok(paths) => ApiResponse::success(paths),
+                Err(e) => ApiResponse::error(e.to_string()),
+            }
+        }
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Apply an mbox patch series as commits
+#[tauri::command]
+pub fn apply_mailbox(repo_path: String, mbox_path: String) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.apply_mailbox(&mbox_path) {
+            Ok(commits) => ApiResponse::success(commits),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
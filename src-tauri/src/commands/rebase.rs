@@ -0,0 +1,103 @@
+//! Rebase commands
+//!
+//! Commands for interactive rebase: planning, executing and resuming after conflicts,
+//! plus rewording older commits and autosquash.
+
+use crate::git_ops::{GitRepository, RebaseTodoItem, RebaseStatus};
+use super::response::ApiResponse;
+
+/// Get the default todo list for rebasing the current branch onto upstream
+#[tauri::command]
+pub fn get_rebase_plan(repo_path: String, upstream: String) -> ApiResponse<Vec<RebaseTodoItem>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_rebase_plan(&upstream) {
+            Ok(items) => ApiResponse::success(items),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Start an interactive rebase with the given todo list
+#[tauri::command]
+pub fn rebase_interactive(repo_path: String, onto: String, items: Vec<RebaseTodoItem>) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", &format!("Rebase onto {}", onto), |r| r.rebase_interactive(&onto, items)) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Continue the in-progress rebase
+#[tauri::command]
+pub fn rebase_continue(repo_path: String) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", "Rebase", |r| r.rebase_continue()) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Skip the current commit of the in-progress rebase
+#[tauri::command]
+pub fn rebase_skip(repo_path: String) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", "Rebase", |r| r.rebase_skip()) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Abort the in-progress rebase
+#[tauri::command]
+pub fn rebase_abort(repo_path: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", "Rebase", |r| r.rebase_abort()) {
+            Ok(_) => ApiResponse::success("Rebase aborted successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Get the status of the in-progress rebase
+#[tauri::command]
+pub fn get_rebase_status(repo_path: String) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_rebase_status() {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Reword a commit on the current branch
+#[tauri::command]
+pub fn reword_commit(repo_path: String, commit_hash: String, message: String) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", &format!("Reword {}", commit_hash), |r| r.reword_commit(&commit_hash, &message)) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Rebase onto upstream, folding fixup!/squash! commits into their targets
+#[tauri::command]
+pub fn rebase_autosquash(repo_path: String, upstream: String) -> ApiResponse<RebaseStatus> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("rebase", &format!("Autosquash onto {}", upstream), |r| r.rebase_autosquash(&upstream)) {
+            Ok(status) => ApiResponse::success(status),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
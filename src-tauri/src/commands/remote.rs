@@ -3,7 +3,7 @@
 //! Commands for remote repository operations.
 
 use tauri::Window;
-use crate::git_ops::{GitRepository, RemoteInfo, AuthConfig};
+use crate::git_ops::{GitRepository, RemoteInfo, AuthConfig, HookOptions};
 use super::response::ApiResponse;
 
 /// Fetch from a remote (异步执行，不阻塞主线程)
@@ -28,9 +28,10 @@ pub async fn fetch_remote(window: Window, repo_path: String, remote_name: String
 /// Pull from a remote (异步执行，不阻塞主线程)
 #[tauri::command]
 pub async fn pull_remote(window: Window, repo_path: String, remote_name: String, branch_name: String, auth_config: Option<AuthConfig>) -> ApiResponse<String> {
+    let hooks = HookOptions { no_verify: false, window: Some(window.clone()) };
     let handle = tokio::task::spawn(async move {
         match GitRepository::open(&repo_path) {
-            Ok(repo) => match repo.pull_with_progress(&remote_name, &branch_name, window, auth_config) {
+            Ok(repo) => match repo.with_hooks(hooks).pull_with_progress(&remote_name, &branch_name, window, auth_config) {
                 Ok(_) => ApiResponse::success("Pull completed".to_string()),
                 Err(e) => ApiResponse::error(e.to_string()),
             },
@@ -44,9 +45,16 @@ pub async fn pull_remote(window: Window, repo_path: String, remote_name: String,
     }
 }
 
-/// Push to a remote (异步执行，不阻塞主线程)
+/// Push to a remote (异步执行，不阻塞主线程；`no_verify` 跳过 pre-push hook)
 #[tauri::command]
-pub async fn push_remote(_window: Window, repo_path: String, remote_name: String, branch_name: String, auth_config: Option<AuthConfig>) -> ApiResponse<String> {
+pub async fn push_remote(
+    window: Window,
+    repo_path: String,
+    remote_name: String,
+    branch_name: String,
+    auth_config: Option<AuthConfig>,
+    no_verify: Option<bool>,
+) -> ApiResponse<String> {
     eprintln!("🚀 push_remote called: repo={}, remote={}, branch={}", repo_path, remote_name, branch_name);
     if let Some(ref auth) = auth_config {
         eprintln!("   认证配置：类型={}, 有token={}", auth.auth_type, auth.token.is_some());
@@ -54,6 +62,8 @@ pub async fn push_remote(_window: Window, repo_path: String, remote_name: String
         eprintln!("   未提供认证配置，将使用默认方式");
     }
 
+    let hooks = HookOptions { no_verify: no_verify.unwrap_or(false), window: Some(window) };
+
     // 添加 30 秒超时
     let timeout_duration = std::time::Duration::from_secs(30);
 
@@ -61,6 +71,7 @@ pub async fn push_remote(_window: Window, repo_path: String, remote_name: String
         eprintln!("📦 Opening repository in blocking thread: {}", repo_path);
         match GitRepository::open(&repo_path) {
             Ok(repo) => {
+                let repo = repo.with_hooks(hooks);
                 eprintln!("✅ Repository opened, starting push (without progress)...");
                 // 使用不带进度的 push 方法，传递认证配置
                 match repo.push(&remote_name, &branch_name, auth_config) {
@@ -2,7 +2,12 @@
 //!
 //! Commands for opening, initializing, cloning, and detecting repository types.
 
-use crate::git_ops::GitRepository;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tauri::Window;
+use crate::git_ops::{AuthConfig, CloneOptions, GitRepository, InitOptions};
 use super::response::ApiResponse;
 
 /// Open an existing repository
@@ -14,38 +19,79 @@ pub fn open_repository(path: String) -> ApiResponse<String> {
     }
 }
 
-/// Clone a repository from URL (异步执行，不阻塞主线程)
+lazy_static::lazy_static! {
+    /// Cancel flags of running clones, by destination path
+    static ref CLONE_CANCELS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+/// Clone a repository from URL (异步执行，不阻塞主线程；通过 git-progress 事件报告进度，可用 cancel_clone 取消)
 #[tauri::command]
-pub async fn clone_repository(url: String, path: String) -> ApiResponse<String> {
-    let handle = tokio::task::spawn(async move {
-        match GitRepository::clone(&url, &path) {
+pub async fn clone_repository(
+    window: Window,
+    url: String,
+    path: String,
+    options: Option<CloneOptions>,
+    auth_config: Option<AuthConfig>,
+) -> ApiResponse<String> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    if let Ok(mut cancels) = CLONE_CANCELS.lock() {
+        cancels.insert(path.clone(), cancel.clone());
+    }
+
+    let clone_path = path.clone();
+    let handle = tokio::task::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        match GitRepository::clone_with_progress(&url, &clone_path, &options, Some(window), auth_config, cancel) {
             Ok(_) => ApiResponse::success("Repository cloned successfully".to_string()),
             Err(e) => ApiResponse::error(e.to_string()),
         }
     });
 
-    match handle.await {
+    let response = match handle.await {
         Ok(response) => response,
         Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    };
+    if let Ok(mut cancels) = CLONE_CANCELS.lock() {
+        cancels.remove(&path);
     }
+    response
 }
 
-/// Initialize a new repository
+/// Cancel a running clone into `path`
 #[tauri::command]
-pub fn init_repository(path: String, default_branch: Option<String>) -> ApiResponse<String> {
-    match GitRepository::init(&path) {
-        Ok(_repo) => {
-            if let Some(branch_name) = default_branch {
-                if branch_name != "master" && branch_name != "main" {
-                    // Branch will be created on first commit
-                }
+pub fn cancel_clone(path: String) -> ApiResponse<String> {
+    match CLONE_CANCELS.lock() {
+        Ok(cancels) => match cancels.get(&path) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                ApiResponse::success("Clone cancellation requested".to_string())
             }
-            ApiResponse::success("Repository initialized successfully".to_string())
+            None => ApiResponse::error(format!("No clone in progress for {}", path)),
         },
         Err(e) => ApiResponse::error(e.to_string()),
     }
 }
 
+/// Initialize a new repository (`options` 可选：裸仓库、模板目录以及带 .gitignore/README/LICENSE 的初始提交)
+#[tauri::command]
+pub fn init_repository(path: String, default_branch: Option<String>, options: Option<InitOptions>) -> ApiResponse<String> {
+    let mut options = options.unwrap_or_default();
+    if options.default_branch.is_none() {
+        options.default_branch = default_branch.filter(|branch| !branch.trim().is_empty());
+    }
+    if options.gitignore.as_deref() == Some("auto") {
+        let files: Vec<String> = std::fs::read_dir(&path)
+            .map(|entries| entries.flatten().filter_map(|e| e.file_name().into_string().ok()).collect())
+            .unwrap_or_default();
+        options.gitignore = Some(detect_type_from_files(&files));
+    }
+
+    match GitRepository::init(&path, &options) {
+        Ok(_) => ApiResponse::success("Repository initialized successfully".to_string()),
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
 /// Detect project type based on files in directory
 #[tauri::command]
 pub fn detect_project_type(path: String) -> ApiResponse<String> {
@@ -0,0 +1,42 @@
+//! Reset commands
+//!
+//! Commands for resetting the current branch and individual paths.
+
+use crate::git_ops::{GitRepository, ResetResult};
+use super::response::ApiResponse;
+
+/// Reset the current branch to a commit in soft, mixed or hard mode
+#[tauri::command]
+pub fn reset_to_commit(repo_path: String, target: String, mode: String) -> ApiResponse<ResetResult> {
+    match GitRepository::open(&repo_path) {
+        Ok(mut repo) => match repo.record_operation("reset", &format!("Reset ({}) to {}", mode, target), |r| r.reset(&target, &mode)) {
+            Ok(result) => ApiResponse::success(result),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Undo a hard reset using the result returned by `reset_to_commit`
+#[tauri::command]
+pub fn undo_hard_reset(repo_path: String, previous_head: String, snapshot: Option<String>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.undo_hard_reset(&previous_head, snapshot.as_deref()) {
+            Ok(_) => ApiResponse::success("Reset undone successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Reset paths in the index to their state in a commit
+#[tauri::command]
+pub fn reset_paths(repo_path: String, commit: String, paths: Vec<String>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.reset_paths(&commit, &paths) {
+            Ok(_) => ApiResponse::success("Paths reset successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
@@ -0,0 +1,99 @@
+//! Sparse checkout commands
+//!
+//! Commands for reading and editing which directories are checked out.
+
+use crate::git_ops::{GitRepository, SparseCheckoutInfo};
+use super::response::ApiResponse;
+
+/// Get the sparse-checkout mode and patterns
+#[tauri::command]
+pub fn get_sparse_checkout(repo_path: String) -> ApiResponse<SparseCheckoutInfo> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_sparse_checkout() {
+            Ok(info) => ApiResponse::success(info),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Enable sparse checkout with the given directories (cone mode, default) or patterns (异步执行，部分克隆时可能需要下载对象)
+#[tauri::command]
+pub async fn set_sparse_checkout(repo_path: String, patterns: Vec<String>, cone: Option<bool>) -> ApiResponse<SparseCheckoutInfo> {
+    let task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.set_sparse_checkout(&patterns, cone.unwrap_or(true)) {
+                Ok(info) => ApiResponse::success(info),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// Add directories to the sparse checkout (异步执行，部分克隆时可能需要下载对象)
+#[tauri::command]
+pub async fn add_sparse_directories(repo_path: String, directories: Vec<String>) -> ApiResponse<SparseCheckoutInfo> {
+    let task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.add_sparse_directories(&directories) {
+                Ok(info) => ApiResponse::success(info),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// Remove directories from the sparse checkout
+#[tauri::command]
+pub fn remove_sparse_directories(repo_path: String, directories: Vec<String>) -> ApiResponse<SparseCheckoutInfo> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.remove_sparse_directories(&directories) {
+            Ok(info) => ApiResponse::success(info),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Reapply the sparse-checkout patterns to the working directory
+#[tauri::command]
+pub fn reapply_sparse_checkout(repo_path: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.reapply_sparse_checkout() {
+            Ok(_) => ApiResponse::success("Sparse checkout reapplied".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Disable sparse checkout and check out every file (异步执行，部分克隆时可能需要下载对象)
+#[tauri::command]
+pub async fn disable_sparse_checkout(repo_path: String) -> ApiResponse<String> {
+    let task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.disable_sparse_checkout() {
+                Ok(_) => ApiResponse::success("Sparse checkout disabled".to_string()),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
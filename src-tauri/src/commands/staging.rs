@@ -1,8 +1,10 @@
 //! Staging commands
 //!
-//! Commands for staging area operations (status, stage, unstage, commit).
+//! Commands for staging area operations (status, stage, unstage, commit),
+//! including hunk- and line-level staging.
 
-use crate::git_ops::{GitRepository, FileChange, CommitInfo};
+use tauri::Window;
+use crate::git_ops::{GitRepository, FileChange, CommitInfo, CommitDetails, CommitPage, CommitQuery, DiffSettings, LineSelection, SignatureVerification, HookOptions};
 use super::response::ApiResponse;
 
 /// Get repository status
@@ -53,18 +55,155 @@ pub fn discard_file(repo_path: String, file_path: String) -> ApiResponse<String>
     }
 }
 
-/// Commit changes
+/// Stage a single hunk of a file
 #[tauri::command]
-pub fn commit_changes(repo_path: String, message: String) -> ApiResponse<String> {
+pub fn stage_hunk(repo_path: String, file_path: String, hunk_index: usize, options: Option<DiffSettings>) -> ApiResponse<String> {
     match GitRepository::open(&repo_path) {
-        Ok(repo) => match repo.commit(&message) {
-            Ok(oid) => ApiResponse::success(oid),
+        Ok(repo) => match repo.stage_hunk(&file_path, hunk_index, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Hunk staged successfully".to_string()),
             Err(e) => ApiResponse::error(e.to_string()),
         },
         Err(e) => ApiResponse::error(e.to_string()),
     }
 }
 
+/// Unstage a single hunk of a file
+#[tauri::command]
+pub fn unstage_hunk(repo_path: String, file_path: String, hunk_index: usize, options: Option<DiffSettings>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.unstage_hunk(&file_path, hunk_index, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Hunk unstaged successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Discard a single hunk of a file
+#[tauri::command]
+pub fn discard_hunk(repo_path: String, file_path: String, hunk_index: usize, options: Option<DiffSettings>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.discard_hunk(&file_path, hunk_index, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Hunk discarded successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Stage selected lines of a file
+#[tauri::command]
+pub fn stage_lines(repo_path: String, file_path: String, lines: Vec<LineSelection>, options: Option<DiffSettings>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.stage_lines(&file_path, &lines, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Lines staged successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Unstage selected lines of a file
+#[tauri::command]
+pub fn unstage_lines(repo_path: String, file_path: String, lines: Vec<LineSelection>, options: Option<DiffSettings>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.unstage_lines(&file_path, &lines, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Lines unstaged successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Discard selected lines of a file
+#[tauri::command]
+pub fn discard_lines(repo_path: String, file_path: String, lines: Vec<LineSelection>, options: Option<DiffSettings>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.discard_lines(&file_path, &lines, &options.unwrap_or_default()) {
+            Ok(_) => ApiResponse::success("Lines discarded successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Commit changes (`no_verify` skips the pre-commit and commit-msg hooks)
+#[tauri::command]
+pub async fn commit_changes(window: Window, repo_path: String, message: String, no_verify: Option<bool>) -> ApiResponse<String> {
+    let hooks = HookOptions { no_verify: no_verify.unwrap_or(false), window: Some(window) };
+
+    // 钩子可能运行很久（lint-staged 等），在后台线程执行以免阻塞界面
+    let commit_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.with_hooks(hooks).record_operation(
+                "commit",
+                &format!("Commit: {}", message.lines().next().unwrap_or("")),
+                |r| r.commit(&message),
+            ) {
+                Ok(oid) => ApiResponse::success(oid),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match commit_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// Amend the last commit with the staged changes and an optional new message
+#[tauri::command]
+pub async fn amend_commit(window: Window, repo_path: String, message: Option<String>, no_verify: Option<bool>) -> ApiResponse<String> {
+    let hooks = HookOptions { no_verify: no_verify.unwrap_or(false), window: Some(window) };
+    let amend_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.with_hooks(hooks).record_operation("commit", "Amend last commit", |r| r.commit_amend(message.as_deref())) {
+                Ok(oid) => ApiResponse::success(oid),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match amend_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// Create a fixup! or squash! commit for a target commit
+#[tauri::command]
+pub async fn create_fixup_commit(
+    window: Window,
+    repo_path: String,
+    target_commit: String,
+    squash: bool,
+    message: Option<String>,
+    no_verify: Option<bool>,
+) -> ApiResponse<String> {
+    let hooks = HookOptions { no_verify: no_verify.unwrap_or(false), window: Some(window) };
+    let fixup_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.with_hooks(hooks).record_operation(
+                "commit",
+                &format!("Fixup commit for {}", target_commit),
+                |r| r.commit_fixup(&target_commit, squash, message.as_deref()),
+            ) {
+                Ok(oid) => ApiResponse::success(oid),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match fixup_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
 /// Get commit history
 #[tauri::command]
 pub fn get_commits(repo_path: String, max_count: usize) -> ApiResponse<Vec<CommitInfo>> {
@@ -76,3 +215,39 @@ pub fn get_commits(repo_path: String, max_count: usize) -> ApiResponse<Vec<Commi
         Err(e) => ApiResponse::error(e.to_string()),
     }
 }
+
+/// Query the commit log with filters and cursor pagination
+#[tauri::command]
+pub fn query_commits(repo_path: String, query: CommitQuery) -> ApiResponse<CommitPage> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.query_commits(&query) {
+            Ok(page) => ApiResponse::success(page),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Get the details of a commit, including its changed files
+#[tauri::command]
+pub fn get_commit_details(repo_path: String, commit_hash: String) -> ApiResponse<CommitDetails> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_commit_details(&commit_hash) {
+            Ok(details) => ApiResponse::success(details),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Verify the signature of a commit
+#[tauri::command]
+pub fn verify_commit_signature(repo_path: String, commit_hash: String) -> ApiResponse<SignatureVerification> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.verify_commit_signature(&commit_hash) {
+            Ok(result) => ApiResponse::success(result),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
@@ -0,0 +1,84 @@
+//! Submodule commands
+//!
+//! Commands for listing, initializing, updating and syncing submodules.
+
+use crate::git_ops::{GitRepository, AuthConfig, SubmoduleInfo, SubmoduleDiff};
+use super::response::ApiResponse;
+
+/// List submodules (`recursive` includes nested ones)
+#[tauri::command]
+pub fn list_submodules(repo_path: String, recursive: Option<bool>) -> ApiResponse<Vec<SubmoduleInfo>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.list_submodules(recursive.unwrap_or(false)) {
+            Ok(submodules) => ApiResponse::success(submodules),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Initialize submodules (all of them when `paths` is empty)
+#[tauri::command]
+pub fn init_submodules(repo_path: String, paths: Option<Vec<String>>) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.init_submodules(&paths.unwrap_or_default()) {
+            Ok(initialized) => ApiResponse::success(initialized),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Update submodules to their recorded commits (异步执行，不阻塞主线程)
+#[tauri::command]
+pub async fn update_submodules(
+    repo_path: String,
+    paths: Option<Vec<String>>,
+    init: Option<bool>,
+    recursive: Option<bool>,
+    auth_config: Option<AuthConfig>,
+) -> ApiResponse<Vec<String>> {
+    let update_task = tokio::task::spawn_blocking(move || {
+        match GitRepository::open(&repo_path) {
+            Ok(repo) => match repo.update_submodules(
+                &paths.unwrap_or_default(),
+                init.unwrap_or(true),
+                recursive.unwrap_or(false),
+                auth_config,
+            ) {
+                Ok(updated) => ApiResponse::success(updated),
+                Err(e) => ApiResponse::error(e.to_string()),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        }
+    });
+
+    match update_task.await {
+        Ok(response) => response,
+        Err(e) => ApiResponse::error(format!("Task execution failed: {}", e)),
+    }
+}
+
+/// Sync submodule URLs from .gitmodules
+#[tauri::command]
+pub fn sync_submodules(repo_path: String, recursive: Option<bool>) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.sync_submodules(recursive.unwrap_or(false)) {
+            Ok(synced) => ApiResponse::success(synced),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Get the commits covered by a submodule pointer change
+#[tauri::command]
+pub fn get_submodule_diff(repo_path: String, path: String, staged: bool) -> ApiResponse<SubmoduleDiff> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_submodule_diff(&path, staged) {
+            Ok(diff) => ApiResponse::success(diff),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
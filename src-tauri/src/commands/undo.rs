@@ -0,0 +1,79 @@
+//! Undo commands
+//!
+//! Commands for reading reflogs, rolling back recorded operations and
+//! recovering lost commits.
+
+use crate::git_ops::{GitRepository, LostCommit, ReflogEntry, UndoEntry};
+use super::response::ApiResponse;
+
+/// List HEAD and the branches that have a reflog
+#[tauri::command]
+pub fn get_reflog_refs(repo_path: String) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_reflog_refs() {
+            Ok(refs) => ApiResponse::success(refs),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Read the reflog of HEAD or a branch
+#[tauri::command]
+pub fn get_reflog(repo_path: String, reference: Option<String>, max_count: Option<usize>) -> ApiResponse<Vec<ReflogEntry>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_reflog(reference.as_deref().unwrap_or("HEAD"), max_count) {
+            Ok(entries) => ApiResponse::success(entries),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Get the recorded operations that can be undone, newest first
+#[tauri::command]
+pub fn get_undo_history(repo_path: String) -> ApiResponse<Vec<UndoEntry>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.get_undo_history() {
+            Ok(entries) => ApiResponse::success(entries),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Roll back the most recent recorded operation
+#[tauri::command]
+pub fn undo_last_operation(repo_path: String) -> ApiResponse<UndoEntry> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.undo_last_operation() {
+            Ok(entry) => ApiResponse::success(entry),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Find commits that are no longer reachable from any branch or tag
+#[tauri::command]
+pub fn find_lost_commits(repo_path: String, max_count: Option<usize>) -> ApiResponse<Vec<LostCommit>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.find_lost_commits(max_count) {
+            Ok(commits) => ApiResponse::success(commits),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Re-create a branch at a lost commit
+#[tauri::command]
+pub fn recover_branch(repo_path: String, commit_hash: String, branch_name: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.recover_branch(&commit_hash, &branch_name) {
+            Ok(_) => ApiResponse::success("Branch recovered successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
@@ -0,0 +1,108 @@
+//! Worktree commands
+//!
+//! Commands for managing linked worktrees.
+
+use crate::git_ops::{GitRepository, WorktreeInfo};
+use super::response::ApiResponse;
+
+/// List the main worktree and all linked worktrees
+#[tauri::command]
+pub fn list_worktrees(repo_path: String) -> ApiResponse<Vec<WorktreeInfo>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.list_worktrees() {
+            Ok(worktrees) => ApiResponse::success(worktrees),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Add a linked worktree for a new or existing branch, or detached at a commit
+#[tauri::command]
+pub fn add_worktree(
+    repo_path: String,
+    path: String,
+    branch: Option<String>,
+    create_branch: Option<bool>,
+    start_point: Option<String>,
+    detach: Option<bool>,
+    lock: Option<bool>,
+) -> ApiResponse<WorktreeInfo> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.add_worktree(
+            &path,
+            branch.as_deref(),
+            create_branch.unwrap_or(false),
+            start_point.as_deref(),
+            detach.unwrap_or(false),
+            lock.unwrap_or(false),
+        ) {
+            Ok(worktree) => ApiResponse::success(worktree),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Lock a linked worktree
+#[tauri::command]
+pub fn lock_worktree(repo_path: String, name: String, reason: Option<String>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.lock_worktree(&name, reason.as_deref()) {
+            Ok(_) => ApiResponse::success("Worktree locked successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Unlock a linked worktree
+#[tauri::command]
+pub fn unlock_worktree(repo_path: String, name: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.unlock_worktree(&name) {
+            Ok(_) => ApiResponse::success("Worktree unlocked successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Prune worktrees whose directory no longer exists
+#[tauri::command]
+pub fn prune_worktrees(repo_path: String) -> ApiResponse<Vec<String>> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.prune_worktrees() {
+            Ok(pruned) => ApiResponse::success(pruned),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Remove a linked worktree and delete its directory
+#[tauri::command]
+pub fn remove_worktree(repo_path: String, name: String, force: Option<bool>) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.remove_worktree(&name, force.unwrap_or(false)) {
+            Ok(_) => ApiResponse::success("Worktree removed successfully".to_string()),
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
+
+/// Open a linked worktree as a repository and return its path
+#[tauri::command]
+pub fn open_worktree(repo_path: String, name: String) -> ApiResponse<String> {
+    match GitRepository::open(&repo_path) {
+        Ok(repo) => match repo.open_worktree(&name) {
+            Ok(worktree) => match worktree.repo.workdir() {
+                Some(path) => ApiResponse::success(path.display().to_string()),
+                None => ApiResponse::error(format!("Worktree '{}' has no working directory", name)),
+            },
+            Err(e) => ApiResponse::error(e.to_string()),
+        },
+        Err(e) => ApiResponse::error(e.to_string()),
+    }
+}
@@ -0,0 +1,508 @@
+//! Bisect operations
+//!
+//! This module drives a binary search for the commit that introduced a bug.
+//! State is kept where the git CLI keeps it (`.git/BISECT_*` files and
+//! `refs/bisect/*`), so a session can be continued from either side.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use git2::{build::CheckoutBuilder, Oid, Sort};
+
+use super::repository::GitRepository;
+use super::types::{BisectRunResult, BisectRunStep, BisectStatus};
+
+/// Files git creates for a bisect session
+const BISECT_FILES: &[&str] = &[
+    "BISECT_START",
+    "BISECT_LOG",
+    "BISECT_TERMS",
+    "BISECT_NAMES",
+    "BISECT_EXPECTED_REV",
+    "BISECT_ANCESTORS_OK",
+    "BISECT_RUN",
+];
+
+/// Longest tail of a run command's output kept per step
+const MAX_RUN_OUTPUT: usize = 4000;
+
+/// Outcome of narrowing down the candidates
+struct BisectPlan {
+    candidates: usize,
+    next: Option<Oid>,
+    first_bad: Option<Oid>,
+    only_skipped_left: Vec<Oid>,
+}
+
+impl GitRepository {
+    /// Start bisecting between a bad commit and one or more good commits
+    pub fn bisect_start(&self, bad: &str, good: &[String]) -> Result<BisectStatus> {
+        if self.bisect_in_progress() {
+            anyhow::bail!("A bisect is already in progress. Please reset it first.");
+        }
+        if good.is_empty() {
+            anyhow::bail!("At least one good commit is required to start bisecting");
+        }
+        self.ensure_clean_worktree("start bisecting")?;
+
+        let bad_id = self.resolve_bisect_commit(bad)?;
+        let mut good_ids = Vec::new();
+        for rev in good {
+            let id = self.resolve_bisect_commit(rev)?;
+            if !self.repo.graph_descendant_of(bad_id, id)? {
+                anyhow::bail!("Good commit {} is not an ancestor of bad commit {}", rev, bad);
+            }
+            good_ids.push(id);
+        }
+
+        // Remember where to return to, as the branch name when HEAD is attached
+        let head = self.repo.head().context("Cannot bisect: repository has no commits")?;
+        let start = if head.is_branch() {
+            head.shorthand().unwrap_or("HEAD").to_string()
+        } else {
+            head.target().map(|oid| oid.to_string()).unwrap_or_default()
+        };
+        drop(head);
+
+        let quoted: Vec<String> = std::iter::once(bad)
+            .chain(good.iter().map(String::as_str))
+            .map(|rev| format!("'{}'", rev))
+            .collect();
+        fs::write(self.bisect_file("BISECT_START"), format!("{}\n", start))?;
+        fs::write(self.bisect_file("BISECT_TERMS"), "bad\ngood\n")?;
+        fs::write(self.bisect_file("BISECT_NAMES"), "\n")?;
+        fs::write(self.bisect_file("BISECT_LOG"), format!("git bisect start {}\n", quoted.join(" ")))?;
+
+        self.write_bisect_mark("bad", bad_id)?;
+        for id in good_ids {
+            self.write_bisect_mark("good", id)?;
+        }
+
+        self.bisect_next()
+    }
+
+    /// Mark a commit (HEAD by default) as `"good"`, `"bad"` or `"skip"` and check out the next one
+    pub fn bisect_mark(&self, verdict: &str, commit: Option<&str>) -> Result<BisectStatus> {
+        if !self.bisect_in_progress() {
+            anyhow::bail!("No bisect in progress");
+        }
+        if !matches!(verdict, "good" | "bad" | "skip") {
+            anyhow::bail!("Unknown bisect verdict: {}", verdict);
+        }
+
+        let id = self.resolve_bisect_commit(commit.unwrap_or("HEAD"))?;
+        if verdict == "good" {
+            if let (Some(bad), _, _) = self.bisect_marks()? {
+                if id == bad || !self.repo.graph_descendant_of(bad, id)? {
+                    anyhow::bail!("Good commit {} is not an ancestor of bad commit {}", id, bad);
+                }
+            }
+        }
+        self.write_bisect_mark(verdict, id)?;
+        self.bisect_next()
+    }
+
+    /// Current bisect state without moving HEAD
+    pub fn bisect_status(&self) -> Result<BisectStatus> {
+        if !self.bisect_in_progress() {
+            return Ok(BisectStatus {
+                active: false,
+                current: None,
+                bad: None,
+                good: Vec::new(),
+                skipped: Vec::new(),
+                remaining: 0,
+                steps: 0,
+                first_bad: None,
+                only_skipped_left: Vec::new(),
+            });
+        }
+
+        let plan = self.bisect_plan()?;
+        let current = self.repo.head().ok().and_then(|h| h.target());
+        self.bisect_status_from(&plan, current)
+    }
+
+    /// End the session and return to the branch or commit bisecting started from
+    pub fn bisect_reset(&self) -> Result<()> {
+        if !self.bisect_in_progress() {
+            anyhow::bail!("No bisect in progress");
+        }
+
+        let start = fs::read_to_string(self.bisect_file("BISECT_START"))?.trim().to_string();
+        let branch_ref = format!("refs/heads/{}", start);
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe();
+
+        if self.repo.find_reference(&branch_ref).is_ok() {
+            if self.is_sparse_checkout() {
+                self.checkout_sparse_branch(&start)
+                    .context("Failed to check out the original branch")?;
+            } else {
+                let target = self.repo.revparse_single(&branch_ref)?;
+                self.repo.checkout_tree(&target, Some(&mut checkout))
+                    .context("Failed to check out the original branch")?;
+                self.repo.set_head(&branch_ref)?;
+            }
+        } else {
+            let oid = Oid::from_str(&start)
+                .context(format!("Cannot return to '{}': branch no longer exists", start))?;
+            let commit = self.repo.find_commit(oid)?;
+            self.checkout_detached(&commit, Some(&mut checkout))
+                .context("Failed to check out the original commit")?;
+        }
+
+        let mut bisect_refs = Vec::new();
+        for reference in self.repo.references_glob("refs/bisect/*")? {
+            bisect_refs.push(reference?);
+        }
+        for mut reference in bisect_refs {
+            reference.delete()?;
+        }
+        for name in BISECT_FILES {
+            let _ = fs::remove_file(self.bisect_file(name));
+        }
+        Ok(())
+    }
+
+    /// Run `command` at each step and mark the commit from its exit code
+    ///
+    /// Exit code 0 means good, 125 means skip and any other code below 128 means
+    /// bad, like `git bisect run`. Higher codes or a killed command stop the run.
+    pub fn bisect_run(&self, command: &str) -> Result<BisectRunResult> {
+        let mut status = self.bisect_status()?;
+        if !status.active {
+            anyhow::bail!("No bisect in progress");
+        }
+
+        let workdir = self.repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Cannot bisect in a bare repository"))?
+            .to_path_buf();
+        let mut steps = Vec::new();
+
+        while status.first_bad.is_none() && status.only_skipped_left.is_empty() {
+            let Some(commit) = status.current.clone() else { break };
+
+            #[cfg(windows)]
+            let output = Command::new("cmd").args(["/C", command]).current_dir(&workdir).output();
+            #[cfg(not(windows))]
+            let output = Command::new("sh").args(["-c", command]).current_dir(&workdir).output();
+            let output = output.context(format!("Failed to run '{}'", command))?;
+
+            let code = output.status.code();
+            let verdict = match code {
+                Some(0) => "good",
+                Some(125) => "skip",
+                Some(1..=127) => "bad",
+                _ => anyhow::bail!(
+                    "Bisect run stopped: '{}' exited with {} on {}",
+                    command,
+                    code.map(|c| c.to_string()).unwrap_or_else(|| "a signal".to_string()),
+                    commit
+                ),
+            };
+
+            let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            if text.len() > MAX_RUN_OUTPUT {
+                let mut cut = text.len() - MAX_RUN_OUTPUT;
+                while !text.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                text = text[cut..].to_string();
+            }
+
+            steps.push(BisectRunStep {
+                commit: commit.clone(),
+                exit_code: code.unwrap_or(-1),
+                verdict: verdict.to_string(),
+                output: text,
+            });
+            status = self.bisect_mark(verdict, Some(&commit))?;
+        }
+
+        Ok(BisectRunResult { status, steps })
+    }
+
+    /// Check out the next commit to test, or log the first bad commit once found
+    fn bisect_next(&self) -> Result<BisectStatus> {
+        let plan = self.bisect_plan()?;
+
+        if let Some(first_bad) = plan.first_bad {
+            let commit = self.repo.find_commit(first_bad)?;
+            self.append_bisect_log(&format!(
+                "# first bad commit: [{}] {}\n",
+                first_bad,
+                commit.summary().unwrap_or("")
+            ))?;
+        } else if let Some(next) = plan.next {
+            let commit = self.repo.find_commit(next)?;
+            let mut checkout = CheckoutBuilder::new();
+            checkout.safe();
+            self.checkout_detached(&commit, Some(&mut checkout))
+                .context(format!("Failed to check out {}", next))?;
+            fs::write(self.bisect_file("BISECT_EXPECTED_REV"), format!("{}\n", next))?;
+        }
+
+        let current = self.repo.head().ok().and_then(|h| h.target());
+        self.bisect_status_from(&plan, current)
+    }
+
+    /// Narrow the candidates down and pick the commit that halves them best
+    fn bisect_plan(&self) -> Result<BisectPlan> {
+        let (bad, good, skipped) = self.bisect_marks()?;
+        let Some(bad) = bad else {
+            return Ok(BisectPlan { candidates: 0, next: None, first_bad: None, only_skipped_left: Vec::new() });
+        };
+
+        // Candidates: reachable from bad but from no good commit
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+        revwalk.push(bad)?;
+        for id in &good {
+            revwalk.hide(*id)?;
+        }
+        let candidates: Vec<Oid> = revwalk.collect::<std::result::Result<_, _>>()?;
+
+        if candidates.len() <= 1 {
+            return Ok(BisectPlan { candidates: candidates.len(), next: None, first_bad: Some(bad), only_skipped_left: Vec::new() });
+        }
+
+        let skipped: HashSet<Oid> = skipped.into_iter().collect();
+        let testable: Vec<Oid> = candidates.iter()
+            .copied()
+            .filter(|id| *id != bad && !skipped.contains(id))
+            .collect();
+        if testable.is_empty() {
+            return Ok(BisectPlan {
+                candidates: candidates.len(),
+                next: None,
+                first_bad: None,
+                only_skipped_left: candidates,
+            });
+        }
+
+        // Weight of a commit = candidates among its ancestors (itself included);
+        // the best split leaves as many on either side as possible
+        let index: HashMap<Oid, usize> = candidates.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let mut parents = Vec::with_capacity(candidates.len());
+        for id in &candidates {
+            let commit = self.repo.find_commit(*id)?;
+            parents.push(commit.parent_ids().filter_map(|p| index.get(&p).copied()).collect::<Vec<_>>());
+        }
+
+        let total = candidates.len();
+        let weights = bisect_weights(&parents);
+        let mut best = None;
+        let mut best_score = 0;
+        for id in &testable {
+            let weight = weights[index[id]];
+            let score = weight.min(total - weight);
+            if best.is_none() || score > best_score {
+                best = Some(*id);
+                best_score = score;
+            }
+        }
+
+        Ok(BisectPlan { candidates: total, next: best, first_bad: None, only_skipped_left: Vec::new() })
+    }
+
+    fn bisect_status_from(&self, plan: &BisectPlan, current: Option<Oid>) -> Result<BisectStatus> {
+        let (bad, good, skipped) = self.bisect_marks()?;
+        let first_bad = match plan.first_bad {
+            Some(id) => Some(self.commit_info(&self.repo.find_commit(id)?)),
+            None => None,
+        };
+        let steps = if plan.candidates > 1 {
+            (usize::BITS - (plan.candidates - 1).leading_zeros()) as usize
+        } else {
+            0
+        };
+
+        Ok(BisectStatus {
+            active: true,
+            current: current.map(|id| id.to_string()),
+            bad: bad.map(|id| id.to_string()),
+            good: good.iter().map(Oid::to_string).collect(),
+            skipped: skipped.iter().map(Oid::to_string).collect(),
+            remaining: plan.candidates,
+            steps,
+            first_bad,
+            only_skipped_left: plan.only_skipped_left.iter().map(Oid::to_string).collect(),
+        })
+    }
+
+    /// The bad, good and skipped commits recorded under `refs/bisect/`
+    fn bisect_marks(&self) -> Result<(Option<Oid>, Vec<Oid>, Vec<Oid>)> {
+        let mut bad = None;
+        let mut good = Vec::new();
+        let mut skipped = Vec::new();
+
+        for reference in self.repo.references_glob("refs/bisect/*")? {
+            let reference = reference?;
+            let (Some(name), Some(target)) = (reference.name(), reference.target()) else { continue };
+            if name == "refs/bisect/bad" {
+                bad = Some(target);
+            } else if name.starts_with("refs/bisect/good-") {
+                good.push(target);
+            } else if name.starts_with("refs/bisect/skip-") {
+                skipped.push(target);
+            }
+        }
+        Ok((bad, good, skipped))
+    }
+
+    /// Record a verdict as a `refs/bisect/` reference and in `BISECT_LOG`
+    fn write_bisect_mark(&self, verdict: &str, id: Oid) -> Result<()> {
+        let name = match verdict {
+            "bad" => "refs/bisect/bad".to_string(),
+            _ => format!("refs/bisect/{}-{}", verdict, id),
+        };
+        self.repo.reference(&name, id, true, &format!("bisect {}", verdict))?;
+
+        let commit = self.repo.find_commit(id)?;
+        self.append_bisect_log(&format!(
+            "# {}: [{}] {}\ngit bisect {} {}\n",
+            verdict,
+            id,
+            commit.summary().unwrap_or(""),
+            verdict,
+            id
+        ))
+    }
+
+    fn append_bisect_log(&self, text: &str) -> Result<()> {
+        use std::io::Write;
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.bisect_file("BISECT_LOG"))?;
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn resolve_bisect_commit(&self, rev: &str) -> Result<Oid> {
+        Ok(self.repo.revparse_single(rev)
+            .context(format!("无法找到引用: {}", rev))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", rev))?
+            .id())
+    }
+
+    fn bisect_in_progress(&self) -> bool {
+        self.bisect_file("BISECT_START").exists()
+    }
+
+    fn bisect_file(&self, name: &str) -> PathBuf {
+        self.repo.path().join(name)
+    }
+}
+
+/// Number of candidates reachable from each candidate, itself included
+///
+/// `parents` must be in topological order (children first). A single-parent
+/// commit adds one to its parent's weight; only merges, whose parents can share
+/// ancestors, are counted by walking them.
+fn bisect_weights(parents: &[Vec<usize>]) -> Vec<usize> {
+    let mut weights = vec![0; parents.len()];
+    for i in (0..parents.len()).rev() {
+        weights[i] = match parents[i].as_slice() {
+            [] => 1,
+            [parent] => weights[*parent] + 1,
+            _ => count_ancestors(i, parents),
+        };
+    }
+    weights
+}
+
+/// Number of candidates reachable from `start`, itself included
+fn count_ancestors(start: usize, parents: &[Vec<usize>]) -> usize {
+    let mut seen = vec![false; parents.len()];
+    let mut stack = vec![start];
+    let mut count = 0;
+
+    while let Some(i) = stack.pop() {
+        if seen[i] {
+            continue;
+        }
+        seen[i] = true;
+        count += 1;
+        stack.extend(&parents[i]);
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+
+    /// Repository with a linear history of `count` commits, oldest first
+    fn linear_repo(count: usize) -> (tempfile::TempDir, GitRepository, Vec<Oid>) {
+        let (dir, repo) = init_repo();
+        let ids = (0..count)
+            .map(|i| Oid::from_str(&commit_file(&repo, "f.txt", &format!("{}\n", i), &format!("commit {}", i))).unwrap())
+            .collect();
+        (dir, repo, ids)
+    }
+
+    #[test]
+    fn test_count_ancestors_diamond() {
+        // 0 merges 1 and 2, which both come from 3
+        let parents = vec![vec![1, 2], vec![3], vec![3], vec![]];
+        assert_eq!(count_ancestors(0, &parents), 4);
+        assert_eq!(count_ancestors(1, &parents), 2);
+        assert_eq!(count_ancestors(3, &parents), 1);
+    }
+
+    #[test]
+    fn test_bisect_weights_match_ancestor_counts() {
+        // Two diamonds stacked on a chain, children first
+        let parents = vec![vec![1], vec![2, 3], vec![4], vec![4], vec![5, 6], vec![7], vec![7], vec![]];
+        let weights = bisect_weights(&parents);
+        let expected: Vec<usize> = (0..parents.len()).map(|i| count_ancestors(i, &parents)).collect();
+        assert_eq!(weights, expected);
+        assert_eq!(weights[0], 8);
+    }
+
+    #[test]
+    fn test_bisect_plan_halves_linear_history() {
+        let (_dir, repo, ids) = linear_repo(9);
+        repo.bisect_start(&ids[8].to_string(), &[ids[0].to_string()]).unwrap();
+        let plan = repo.bisect_plan().unwrap();
+        assert_eq!(plan.candidates, 8);
+        assert_eq!(plan.next, Some(ids[4]));
+        assert_eq!(plan.first_bad, None);
+    }
+
+    #[test]
+    fn test_bisect_plan_finds_first_bad() {
+        let (_dir, repo, ids) = linear_repo(5);
+        repo.bisect_start(&ids[4].to_string(), &[ids[0].to_string()]).unwrap();
+        repo.bisect_mark("good", Some(&ids[2].to_string())).unwrap();
+        let status = repo.bisect_mark("good", Some(&ids[3].to_string())).unwrap();
+        assert_eq!(status.first_bad.map(|c| c.hash), Some(ids[4].to_string()));
+    }
+
+    #[test]
+    fn test_bisect_plan_reports_only_skipped() {
+        let (_dir, repo, ids) = linear_repo(3);
+        repo.bisect_start(&ids[2].to_string(), &[ids[0].to_string()]).unwrap();
+        let status = repo.bisect_mark("skip", Some(&ids[1].to_string())).unwrap();
+        assert!(status.first_bad.is_none());
+        assert_eq!(status.only_skipped_left.len(), 2);
+    }
+
+    #[test]
+    fn test_bisect_mark_good_must_be_ancestor_of_bad() {
+        let (_dir, repo, ids) = linear_repo(4);
+        repo.bisect_start(&ids[2].to_string(), &[ids[0].to_string()]).unwrap();
+        assert!(repo.bisect_mark("good", Some(&ids[3].to_string())).is_err());
+        assert!(repo.bisect_mark("good", Some(&ids[2].to_string())).is_err());
+        assert!(repo.bisect_mark("good", Some(&ids[1].to_string())).is_ok());
+    }
+}
@@ -18,12 +18,22 @@ impl GitRepository {
         let blame = self.repo.blame_file(Path::new(file_path), None)
             .context(format!("Failed to get blame for file: {}", file_path))?;
 
-        let repo_path = self.repo.path().parent().unwrap_or(self.repo.path());
-        let full_path = repo_path.join(file_path);
-        let file = fs::File::open(&full_path)
-            .context(format!("Failed to open file: {:?}", full_path))?;
+        // The working copy of an LFS file is the binary object; blame its committed pointer instead
+        let reader: Box<dyn BufRead> = if self.is_lfs_path(file_path) {
+            let blob = self.repo.head()?
+                .peel_to_tree()?
+                .get_path(Path::new(file_path))?
+                .to_object(&self.repo)?
+                .peel_to_blob()?;
+            Box::new(BufReader::new(std::io::Cursor::new(blob.content().to_vec())))
+        } else {
+            let repo_path = self.repo.path().parent().unwrap_or(self.repo.path());
+            let full_path = repo_path.join(file_path);
+            let file = fs::File::open(&full_path)
+                .context(format!("Failed to open file: {:?}", full_path))?;
+            Box::new(BufReader::new(file))
+        };
 
-        let reader = BufReader::new(file);
         let mut results = Vec::new();
 
         for (line_number, line_result) in reader.lines().enumerate() {
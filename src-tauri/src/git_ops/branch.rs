@@ -12,6 +12,7 @@ impl GitRepository {
     /// Get all branches (local and remote)
     pub fn get_branches(&self) -> Result<Vec<BranchInfo>> {
         let mut branches = Vec::new();
+        let checked_out = self.worktree_branches()?;
 
         // Local branches
         let local_branches = self.repo.branches(Some(BranchType::Local))?;
@@ -28,12 +29,17 @@ impl GitRepository {
                 .ok()
                 .map(|c| c.id().to_string());
 
+            let worktree = branch.get().name()
+                .and_then(|refname| checked_out.get(refname))
+                .map(|path| path.display().to_string());
+
             branches.push(BranchInfo {
                 name,
                 is_head,
                 is_remote: false,
                 upstream,
                 last_commit,
+                worktree,
             });
         }
 
@@ -53,6 +59,7 @@ impl GitRepository {
                 is_remote: true,
                 upstream: None,
                 last_commit,
+                worktree: None,
             });
         }
 
@@ -71,12 +78,16 @@ impl GitRepository {
     pub fn checkout_branch(&self, name: &str) -> Result<()> {
         use git2::Status;
 
+        self.ensure_no_rebase_in_progress(&format!("checkout branch '{}'", name))?;
+
         // Check for uncommitted changes in working directory
         let statuses = self.repo.statuses(None)?;
         let has_changes = statuses.iter().any(|entry| {
             let status = entry.status();
+            // Files outside the sparse-checkout cone are missing on purpose
+            let sparse_excluded = || entry.path().is_some_and(|path| self.is_sparse_excluded(path));
             status.contains(Status::WT_MODIFIED)
-                || status.contains(Status::WT_DELETED)
+                || (status.contains(Status::WT_DELETED) && !sparse_excluded())
                 || status.contains(Status::WT_NEW)
                 || status.contains(Status::INDEX_MODIFIED)
                 || status.contains(Status::INDEX_NEW)
@@ -91,6 +102,19 @@ impl GitRepository {
             );
         }
 
+        if let Some(path) = self.worktree_branches()?.get(&format!("refs/heads/{}", name)) {
+            anyhow::bail!(
+                "Cannot checkout branch '{}': it is already checked out at {}",
+                name,
+                path.display()
+            );
+        }
+
+        // libgit2 ignores sparse-checkout and would see the excluded files as deleted
+        if self.is_sparse_checkout() {
+            return self.checkout_sparse_branch(name);
+        }
+
         // Proceed with checkout
         let obj = self.repo.revparse_single(&format!("refs/heads/{}", name))?;
         self.repo.checkout_tree(&obj, None)?;
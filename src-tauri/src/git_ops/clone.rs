@@ -0,0 +1,515 @@
+//! Clone operations
+//!
+//! This module clones repositories through libgit2's `RepoBuilder`, reporting
+//! progress and honouring cancellation. Partial clones, sparse checkouts and
+//! shallow clones of local paths need the `git` command instead.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{Direction, FetchOptions, Remote, RemoteCallbacks};
+use tauri::Emitter;
+
+use super::hooks::HookOptions;
+use super::credential_helper::GitCredential;
+use super::credentials::{credential_callbacks, CredentialApproval};
+use super::repository::GitRepository;
+use super::types::{AuthConfig, CloneOptions, GitProgress};
+
+/// Minimum interval between two `git-progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+impl GitRepository {
+    /// Clone a repository, emitting `git-progress` events to `window`
+    ///
+    /// Setting `cancel` aborts the transfer. On failure whatever was written to
+    /// `path` is removed, so the clone can simply be retried.
+    pub fn clone_with_progress(
+        url: &str,
+        path: &str,
+        options: &CloneOptions,
+        window: Option<tauri::Window>,
+        auth_config: Option<AuthConfig>,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        validate_clone_options(options)?;
+
+        let target = Path::new(path);
+        let existed = target.exists();
+        if existed && fs::read_dir(target).map(|mut entries| entries.next().is_some()).unwrap_or(true) {
+            anyhow::bail!("Destination path '{}' already exists and is not an empty directory", path);
+        }
+
+        // libgit2 has no object filters or sparse checkout, and its local transport cannot fetch shallow
+        let needs_git = options.filter.is_some()
+            || options.sparse_directories.is_some()
+            || (options.depth.is_some() && is_local_url(url));
+        let result = if needs_git {
+            Self::clone_with_git(url, path, options, window, auth_config.as_ref(), &cancel)
+        } else {
+            Self::clone_with_builder(url, path, options, window, auth_config, &cancel)
+        };
+
+        result.map_err(|e| {
+            remove_partial_clone(target, existed);
+            if cancel.load(Ordering::SeqCst) {
+                anyhow::anyhow!("Clone cancelled")
+            } else {
+                e
+            }
+        })
+    }
+
+    fn clone_with_builder(
+        url: &str,
+        path: &str,
+        options: &CloneOptions,
+        window: Option<tauri::Window>,
+        auth_config: Option<AuthConfig>,
+        cancel: &Arc<AtomicBool>,
+    ) -> Result<Self> {
+        // Without an explicit branch, a single-branch clone follows the remote's HEAD
+        let single_branch = match (options.single_branch, &options.branch) {
+            (false, _) => None,
+            (true, Some(branch)) => Some(branch.clone()),
+            (true, None) => Some(remote_default_branch(url, auth_config.clone())?),
+        };
+
+        let (callbacks, credentials) = progress_callbacks(auth_config.clone(), window.clone(), cancel.clone());
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+        if let Some(depth) = options.depth {
+            fetch_options.depth(depth as i32);
+        }
+
+        let mut builder = RepoBuilder::new();
+        builder.bare(options.bare || options.mirror);
+        builder.fetch_options(fetch_options);
+        if let Some(branch) = options.branch.as_deref().or(single_branch.as_deref()) {
+            builder.branch(branch);
+        }
+
+        if options.mirror {
+            builder.remote_create(|repo, name, url| {
+                let remote = repo.remote_with_fetch(name, url, "+refs/*:refs/*")?;
+                repo.config()?.set_bool(&format!("remote.{}.mirror", name), true)?;
+                Ok(remote)
+            });
+        } else if let Some(branch) = single_branch {
+            builder.remote_create(move |repo, name, url| {
+                repo.remote_with_fetch(name, url, &format!("+refs/heads/{0}:refs/remotes/{1}/{0}", branch, name))
+            });
+        }
+
+        if let Some(window) = window {
+            let mut last_update = Instant::now();
+            let mut checkout = CheckoutBuilder::new();
+            checkout.progress(move |_path, completed, total| {
+                if last_update.elapsed() >= PROGRESS_INTERVAL || completed == total {
+                    let _ = window.emit("git-progress", GitProgress {
+                        operation_type: "checkout".to_string(),
+                        total_objects: total,
+                        received_objects: completed,
+                        total_bytes: 0,
+                        received_bytes: 0,
+                        speed_bytes_per_sec: 0,
+                    });
+                    last_update = Instant::now();
+                }
+            });
+            builder.with_checkout(checkout);
+        }
+
+        let repo = builder.clone(url, Path::new(path))
+            .context(format!("Failed to clone repository from {}", url))?;
+        credentials.approve();
+        if options.mirror {
+            // libgit2 records the remote HEAD as a remote-tracking ref, which a mirror has none of,
+            // and leaves HEAD on the default branch of a new repository
+            let default_branch = match repo.find_reference("refs/remotes/origin/HEAD") {
+                Ok(mut remote_head) => {
+                    let branch = remote_head.symbolic_target()
+                        .and_then(|target| target.strip_prefix("refs/remotes/origin/"))
+                        .map(String::from);
+                    remote_head.delete()?;
+                    branch
+                }
+                Err(_) => None,
+            };
+            let default_branch = match default_branch {
+                Some(branch) => branch,
+                None => remote_default_branch(url, auth_config.clone())?,
+            };
+            repo.set_head(&format!("refs/heads/{}", default_branch))?;
+        }
+
+        let cloned = Self { repo, hooks: HookOptions::default() };
+        if options.recursive {
+            cloned.update_submodules(&[], true, true, auth_config)?;
+        }
+        Ok(cloned)
+    }
+
+    /// Clone through the `git` command, which supports object filters and sparse checkout
+    fn clone_with_git(
+        url: &str,
+        path: &str,
+        options: &CloneOptions,
+        window: Option<tauri::Window>,
+        auth_config: Option<&AuthConfig>,
+        cancel: &AtomicBool,
+    ) -> Result<Self> {
+        let (mut args, envs) = git_credential_args(url, auth_config);
+        args.extend(["clone".to_string(), "--progress".to_string()]);
+        if let Some(filter) = &options.filter {
+            args.push(format!("--filter={}", filter));
+        }
+        if let Some(branch) = &options.branch {
+            args.push(format!("--branch={}", branch));
+        }
+        if options.single_branch {
+            args.push("--single-branch".to_string());
+        } else if options.depth.is_some() {
+            // `git clone --depth` implies a single branch, unlike the libgit2 path
+            args.push("--no-single-branch".to_string());
+        }
+        if let Some(depth) = options.depth {
+            args.push(format!("--depth={}", depth));
+        }
+        if options.mirror {
+            args.push("--mirror".to_string());
+        } else if options.bare {
+            args.push("--bare".to_string());
+        }
+        if options.sparse_directories.is_some() {
+            args.push("--sparse".to_string());
+        }
+        if options.recursive {
+            args.push("--recurse-submodules".to_string());
+        }
+        args.extend(["--".to_string(), url.to_string(), path.to_string()]);
+
+        run_git_clone(&args, &envs, window, cancel)
+            .context(format!("Failed to clone repository from {}", url))?;
+
+        let cloned = Self::open(path)?;
+        if let Some(directories) = options.sparse_directories.as_ref().filter(|dirs| !dirs.is_empty()) {
+            cloned.set_sparse_checkout(directories, true)?;
+        }
+        Ok(cloned)
+    }
+}
+
+fn validate_clone_options(options: &CloneOptions) -> Result<()> {
+    if let Some(filter) = &options.filter {
+        if !is_valid_filter(filter) {
+            anyhow::bail!("Unsupported clone filter: {}. Use blob:none, tree:0 or blob:limit=<size>", filter);
+        }
+    }
+    if options.depth == Some(0) {
+        anyhow::bail!("Clone depth must be at least 1");
+    }
+    if options.mirror && (options.branch.is_some() || options.single_branch) {
+        anyhow::bail!("A mirror clone copies every reference and cannot be limited to a branch");
+    }
+    if (options.bare || options.mirror) && (options.recursive || options.sparse_directories.is_some()) {
+        anyhow::bail!("Bare and mirror clones have no working directory for submodules or sparse checkout");
+    }
+    Ok(())
+}
+
+fn is_valid_filter(filter: &str) -> bool {
+    match filter {
+        "blob:none" | "tree:0" => true,
+        _ => filter.strip_prefix("blob:limit=").is_some_and(|limit| {
+            let digits = limit.trim_end_matches(['k', 'm', 'g']);
+            !digits.is_empty() && limit.len() - digits.len() <= 1 && digits.bytes().all(|b| b.is_ascii_digit())
+        }),
+    }
+}
+
+/// Whether `url` is served by libgit2's local transport
+fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://") || (!url.contains("://") && Path::new(url).exists())
+}
+
+/// Credential callbacks plus `git-progress` events and cancellation
+fn progress_callbacks<'a>(
+    auth_config: Option<AuthConfig>,
+    window: Option<tauri::Window>,
+    cancel: Arc<AtomicBool>,
+) -> (RemoteCallbacks<'a>, CredentialApproval) {
+    let (mut callbacks, credentials) = credential_callbacks(auth_config, None);
+
+    let sideband_cancel = cancel.clone();
+    callbacks.sideband_progress(move |_| !sideband_cancel.load(Ordering::SeqCst));
+
+    let mut last_update = Instant::now();
+    let mut last_bytes = 0u64;
+    callbacks.transfer_progress(move |stats| {
+        if cancel.load(Ordering::SeqCst) {
+            return false;
+        }
+        let Some(window) = &window else { return true };
+
+        let elapsed = last_update.elapsed();
+        if elapsed >= PROGRESS_INTERVAL {
+            let received_bytes = stats.received_bytes() as u64;
+            let speed = (received_bytes.saturating_sub(last_bytes) as f64 / elapsed.as_secs_f64()) as u64;
+            let _ = window.emit("git-progress", GitProgress {
+                operation_type: "download".to_string(),
+                total_objects: stats.total_objects(),
+                received_objects: stats.received_objects(),
+                total_bytes: received_bytes,
+                received_bytes,
+                speed_bytes_per_sec: speed,
+            });
+            last_update = Instant::now();
+            last_bytes = received_bytes;
+        }
+        true
+    });
+
+    (callbacks, credentials)
+}
+
+/// Branch the remote's HEAD points at
+fn remote_default_branch(url: &str, auth_config: Option<AuthConfig>) -> Result<String> {
+    let mut remote = Remote::create_detached(url)?;
+    let (callbacks, credentials) = credential_callbacks(auth_config, None);
+    let connection = remote.connect_auth(Direction::Fetch, Some(callbacks), None)
+        .context(format!("Failed to connect to {}", url))?;
+    credentials.approve();
+    let head = connection.default_branch()
+        .context("The remote has no default branch; choose the branch to clone")?;
+    let head = head.as_str().unwrap_or_default();
+    Ok(head.strip_prefix("refs/heads/").unwrap_or(head).to_string())
+}
+
+/// Options and environment handing `auth_config` to the `git` command
+///
+/// A one-shot credential helper replaces the configured ones and answers with the
+/// user name and secret from the child's environment, keeping them off its command line.
+fn git_credential_args(url: &str, auth_config: Option<&AuthConfig>) -> (Vec<String>, Vec<(&'static str, String)>) {
+    let Some(auth) = auth_config else {
+        return (Vec::new(), Vec::new());
+    };
+    let secret = match auth.auth_type.as_str() {
+        "token" => auth.token.as_deref(),
+        "password" => auth.password.as_deref(),
+        _ => None,
+    };
+    let Some(secret) = secret.filter(|secret| !secret.is_empty()) else {
+        return (Vec::new(), Vec::new());
+    };
+    let username = auth.username.clone()
+        .filter(|username| !username.is_empty())
+        .or_else(|| GitCredential::from_url(url).username)
+        .unwrap_or_else(|| "git".to_string());
+
+    let helper = "!f() { if test \"$1\" = get; then \
+        printf 'username=%s\\npassword=%s\\n' \"$CAOGIT_CLONE_USERNAME\" \"$CAOGIT_CLONE_PASSWORD\"; fi; }; f";
+    let args = ["-c", "credential.helper=", "-c"]
+        .into_iter()
+        .map(String::from)
+        .chain(std::iter::once(format!("credential.helper={}", helper)))
+        .collect();
+    let envs = vec![("CAOGIT_CLONE_USERNAME", username), ("CAOGIT_CLONE_PASSWORD", secret.to_string())];
+    (args, envs)
+}
+
+/// Run `git clone`, turning its progress output into `git-progress` events
+fn run_git_clone(
+    args: &[String],
+    envs: &[(&str, String)],
+    window: Option<tauri::Window>,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    let mut child = Command::new("git")
+        .args(args)
+        .envs(envs.iter().map(|(key, value)| (key, value)))
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+    let mut stderr = child.stderr.take()
+        .ok_or_else(|| anyhow::anyhow!("Failed to read git output"))?;
+
+    // Progress lines are rewritten in place with '\r'
+    let reader = std::thread::spawn(move || {
+        let mut errors = Vec::new();
+        let mut line = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let mut last_update = Instant::now();
+        while let Ok(read) = stderr.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            for &byte in &buffer[..read] {
+                if byte != b'\r' && byte != b'\n' {
+                    line.push(byte);
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&line).to_string();
+                line.clear();
+                if let Some(progress) = parse_clone_progress(&text) {
+                    if let Some(window) = &window {
+                        if last_update.elapsed() >= PROGRESS_INTERVAL {
+                            let _ = window.emit("git-progress", progress);
+                            last_update = Instant::now();
+                        }
+                    }
+                } else if text.starts_with("fatal:") || text.starts_with("error:") {
+                    errors.push(text);
+                }
+            }
+        }
+        errors
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancel.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Clone cancelled");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    let errors = reader.join().unwrap_or_default();
+    if !status.success() {
+        anyhow::bail!("git clone failed: {}", if errors.is_empty() { status.to_string() } else { errors.join("\n") });
+    }
+    Ok(())
+}
+
+/// Parse a line like `Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s`
+fn parse_clone_progress(line: &str) -> Option<GitProgress> {
+    let rest = line.strip_prefix("Receiving objects:")?;
+    let counts = rest.split('(').nth(1)?.split(')').next()?;
+    let (received, total) = counts.split_once('/')?;
+
+    let transfer = rest.split_once("), ").map(|(_, t)| t.trim_end_matches(", done.")).unwrap_or("");
+    let (size, speed) = transfer.split_once(" | ").unwrap_or((transfer, ""));
+    let received_bytes = parse_size(size).unwrap_or(0);
+
+    Some(GitProgress {
+        operation_type: "download".to_string(),
+        total_objects: total.trim().parse().ok()?,
+        received_objects: received.trim().parse().ok()?,
+        total_bytes: received_bytes,
+        received_bytes,
+        speed_bytes_per_sec: parse_size(speed.trim_end_matches("/s")).unwrap_or(0),
+    })
+}
+
+/// Parse a size printed by git such as `512 bytes` or `1.20 MiB`
+fn parse_size(text: &str) -> Option<u64> {
+    let (value, unit) = text.trim().split_once(' ')?;
+    let value: f64 = value.parse().ok()?;
+    let scale = match unit {
+        "bytes" | "byte" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((value * scale) as u64)
+}
+
+/// Remove what a failed clone left behind, keeping a destination directory that existed before
+fn remove_partial_clone(target: &Path, existed: bool) {
+    if !existed {
+        let _ = fs::remove_dir_all(target);
+        return;
+    }
+    if let Ok(entries) = fs::read_dir(target) {
+        for entry in entries.flatten() {
+            let _ = match entry.file_type() {
+                Ok(kind) if kind.is_dir() => fs::remove_dir_all(entry.path()),
+                _ => fs::remove_file(entry.path()),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_filter() {
+        for filter in ["blob:none", "tree:0", "blob:limit=1024", "blob:limit=1m"] {
+            assert!(is_valid_filter(filter), "{}", filter);
+        }
+        for filter in ["blob:limit=", "blob:limit=1mm", "blob:limit=k", "tree:1", "sparse:oid=abc"] {
+            assert!(!is_valid_filter(filter), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_validate_clone_options() {
+        assert!(validate_clone_options(&CloneOptions::default()).is_ok());
+        assert!(validate_clone_options(&CloneOptions { depth: Some(0), ..Default::default() }).is_err());
+        assert!(validate_clone_options(&CloneOptions { filter: Some("bad".into()), ..Default::default() }).is_err());
+        assert!(validate_clone_options(&CloneOptions {
+            mirror: true,
+            branch: Some("main".into()),
+            ..Default::default()
+        }).is_err());
+        assert!(validate_clone_options(&CloneOptions { bare: true, recursive: true, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_parse_clone_progress() {
+        let progress = parse_clone_progress("Receiving objects:  45% (450/1000), 1.50 MiB | 512.00 KiB/s").unwrap();
+        assert_eq!((progress.received_objects, progress.total_objects), (450, 1000));
+        assert_eq!(progress.received_bytes, 1572864);
+        assert_eq!(progress.speed_bytes_per_sec, 524288);
+
+        let done = parse_clone_progress("Receiving objects: 100% (3/3), done.").unwrap();
+        assert_eq!((done.received_objects, done.total_objects, done.received_bytes), (3, 3, 0));
+
+        assert!(parse_clone_progress("Resolving deltas: 100% (2/2), done.").is_none());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512 bytes"), Some(512));
+        assert_eq!(parse_size("2.00 KiB"), Some(2048));
+        assert_eq!(parse_size("1 GiB"), Some(1 << 30));
+        assert_eq!(parse_size("12 parsecs"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn test_git_credential_args() {
+        let (args, envs) = git_credential_args("https://example.com/repo.git", None);
+        assert!(args.is_empty() && envs.is_empty());
+
+        let auth = AuthConfig {
+            auth_type: "token".into(),
+            token: Some("secret".into()),
+            username: None,
+            password: None,
+        };
+        let (args, envs) = git_credential_args("https://alice@example.com/repo.git", Some(&auth));
+        assert_eq!(&args[..3], ["-c", "credential.helper=", "-c"]);
+        assert!(!args.iter().any(|arg| arg.contains("secret")));
+        assert_eq!(envs, vec![
+            ("CAOGIT_CLONE_USERNAME", "alice".to_string()),
+            ("CAOGIT_CLONE_PASSWORD", "secret".to_string()),
+        ]);
+    }
+}
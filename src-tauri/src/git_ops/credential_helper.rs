@@ -0,0 +1,360 @@
+//! Git credential helpers
+//!
+//! This module speaks the `git credential` protocol with the helpers named in
+//! `credential.helper` (store, cache, osxkeychain, libsecret, manager, ...),
+//! so the app finds, saves and forgets HTTPS credentials exactly as the git
+//! command does. It also implements CaoGit's own helper on top of the
+//! `keychain` module, which git runs as `caogit credential <action>`.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use git2::{Config, Repository};
+
+use super::repository::GitRepository;
+use super::types::CredentialHelperInfo;
+
+/// Argument that makes the app binary act as a credential helper
+const HELPER_ARG: &str = "credential";
+
+/// Prefix of keychain accounts written by the keychain helper
+const KEYCHAIN_PREFIX: &str = "git:";
+
+/// One credential as described by the `git credential` protocol
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct GitCredential {
+    pub protocol: Option<String>,
+    pub host: Option<String>,
+    pub path: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl GitCredential {
+    /// Credential request for `url`, without a password
+    pub fn from_url(url: &str) -> Self {
+        let (protocol, rest) = match url.split_once("://") {
+            Some((protocol, rest)) => (protocol.to_ascii_lowercase(), rest),
+            None => ("ssh".to_string(), url),
+        };
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (userinfo, host) = match authority.rsplit_once('@') {
+            Some((userinfo, host)) => (Some(userinfo), host),
+            None => (None, authority),
+        };
+        let username = userinfo
+            .map(|info| info.split(':').next().unwrap_or(info))
+            .filter(|name| !name.is_empty())
+            .map(String::from);
+
+        Self {
+            protocol: Some(protocol),
+            host: Some(host.to_ascii_lowercase()).filter(|host| !host.is_empty()),
+            path: Some(path.trim_end_matches('/').to_string()).filter(|path| !path.is_empty()),
+            username,
+            password: None,
+        }
+    }
+
+    /// Parse `key=value` lines, stopping at the first blank line
+    pub fn parse(input: &str) -> Self {
+        let mut credential = Self::default();
+        for line in input.lines() {
+            if line.is_empty() {
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let value = Some(value.to_string());
+            match key {
+                "protocol" => credential.protocol = value,
+                "host" => credential.host = value,
+                "path" => credential.path = value,
+                "username" => credential.username = value,
+                "password" => credential.password = value,
+                _ => {}
+            }
+        }
+        credential
+    }
+
+    /// Serialize as helper input, terminated by a blank line
+    pub fn to_input(&self) -> String {
+        let mut input = String::new();
+        for (key, value) in [
+            ("protocol", &self.protocol),
+            ("host", &self.host),
+            ("path", &self.path),
+            ("username", &self.username),
+            ("password", &self.password),
+        ] {
+            if let Some(value) = value {
+                input.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        input.push('\n');
+        input
+    }
+
+    fn is_complete(&self) -> bool {
+        self.username.is_some() && self.password.is_some()
+    }
+
+    /// `protocol://host[/path]`, the key credentials are stored under
+    fn key(&self) -> String {
+        let mut key = format!(
+            "{}://{}",
+            self.protocol.as_deref().unwrap_or(""),
+            self.host.as_deref().unwrap_or("")
+        );
+        if let Some(path) = &self.path {
+            key.push('/');
+            key.push_str(path);
+        }
+        key
+    }
+}
+
+impl GitRepository {
+    /// Credential helpers configured for `url` and whether CaoGit's keychain helper is installed
+    pub fn get_credential_helpers(&self, url: &str) -> Result<CredentialHelperInfo> {
+        let config = Repository::open(self.repo.path())?.config()?;
+        Ok(CredentialHelperInfo {
+            helpers: configured_helpers(&config, &GitCredential::from_url(url)),
+            keychain_helper_installed: keychain_helper_installed(&config),
+        })
+    }
+}
+
+/// Ask the configured helpers for a username and password, as `git credential fill` does
+pub(crate) fn credential_fill(config: &Config, url: &str, username: Option<&str>) -> Option<GitCredential> {
+    let mut request = request_for(config, url);
+    if request.username.is_none() {
+        request.username = username.map(String::from);
+    }
+
+    for helper in configured_helpers(config, &request) {
+        let Ok(output) = run_helper(&helper, "get", &request) else { continue };
+        let answer = GitCredential::parse(&output);
+        if request.username.is_none() {
+            request.username = answer.username;
+        }
+        if answer.password.is_some() {
+            request.password = answer.password;
+        }
+        if request.is_complete() || output.lines().any(|line| line == "quit=true" || line == "quit=1") {
+            break;
+        }
+    }
+    request.is_complete().then_some(request)
+}
+
+/// Hand a credential the server accepted to every helper to store, as `git credential approve` does
+pub(crate) fn credential_approve(config: &Config, credential: &GitCredential) {
+    for helper in configured_helpers(config, credential) {
+        let _ = run_helper(&helper, "store", credential);
+    }
+}
+
+/// Ask every helper to forget a credential the server rejected, as `git credential reject` does
+pub(crate) fn credential_reject(config: &Config, credential: &GitCredential) {
+    for helper in configured_helpers(config, credential) {
+        let _ = run_helper(&helper, "erase", credential);
+    }
+}
+
+/// Credential request for `url`, dropping the path unless `credential.useHttpPath` asks for it
+pub(crate) fn request_for(config: &Config, url: &str) -> GitCredential {
+    let mut request = GitCredential::from_url(url);
+    let is_http = matches!(request.protocol.as_deref(), Some("http" | "https"));
+    if is_http && !config.get_bool("credential.useHttpPath").unwrap_or(false) {
+        request.path = None;
+    }
+    request
+}
+
+/// Helpers from `credential.helper` and matching `credential.<url>.helper`, in config order
+///
+/// An empty value clears the helpers listed before it.
+fn configured_helpers(config: &Config, request: &GitCredential) -> Vec<String> {
+    let mut helpers = Vec::new();
+    let Ok(entries) = config.entries(Some(r"^credential\..*helper$")) else { return helpers };
+    let _ = entries.for_each(|entry| {
+        let Some(name) = entry.name() else { return };
+        let Some(value) = entry.value() else { return };
+        let applies = match name.strip_prefix("credential.").and_then(|n| n.strip_suffix(".helper")) {
+            Some(url) => url_matches(url, request),
+            None => name == "credential.helper",
+        };
+        if !applies {
+            return;
+        }
+        if value.trim().is_empty() {
+            helpers.clear();
+        } else {
+            helpers.push(value.trim().to_string());
+        }
+    });
+    helpers
+}
+
+/// Whether a `credential.<url>` section applies to a request
+fn url_matches(pattern: &str, request: &GitCredential) -> bool {
+    let pattern = GitCredential::from_url(pattern);
+    let eq = |a: &Option<String>, b: &Option<String>| match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => false,
+    };
+    eq(&pattern.protocol, &request.protocol)
+        && eq(&pattern.host, &request.host)
+        && pattern.username.as_ref().is_none_or(|name| request.username.as_ref() == Some(name))
+        && pattern.path.as_ref().is_none_or(|path| {
+            request.path.as_deref().is_some_and(|p| p == path || p.starts_with(&format!("{}/", path)))
+        })
+}
+
+/// Run one helper with `action` and return what it printed
+///
+/// `!command` runs through the shell, an absolute path runs as is, and a
+/// plain name runs `git credential-<name>`, matching git.
+fn run_helper(helper: &str, action: &str, credential: &GitCredential) -> Result<String> {
+    let mut command = if let Some(shell) = helper.strip_prefix('!') {
+        shell_command(&format!("{} {}", shell, action))
+    } else if PathBuf::from(helper.split_whitespace().next().unwrap_or(helper)).is_absolute() {
+        shell_command(&format!("{} {}", helper, action))
+    } else {
+        let mut words = helper.split_whitespace();
+        let mut command = Command::new("git");
+        command.arg(format!("credential-{}", words.next().unwrap_or(helper)));
+        command.args(words).arg(action);
+        command
+    };
+
+    let mut child = command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context(format!("Failed to run credential helper '{}'", helper))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A helper that ignores its input may exit before reading it
+        let _ = stdin.write_all(credential.to_input().as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("Credential helper '{}' failed", helper);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+/// Credential stored by the keychain helper for a request
+pub(crate) fn keychain_get(request: &GitCredential) -> Option<GitCredential> {
+    let stored = crate::keychain::get_password(&format!("{}{}", KEYCHAIN_PREFIX, request.key())).ok()?;
+    let stored = GitCredential::parse(&stored);
+    if request.username.as_ref().is_some_and(|name| stored.username.as_ref() != Some(name)) {
+        return None;
+    }
+    stored.password.is_some().then(|| GitCredential {
+        username: stored.username,
+        password: stored.password,
+        ..request.clone()
+    })
+}
+
+/// Save a credential under `git:protocol://host` in the keychain
+pub(crate) fn keychain_store(credential: &GitCredential) -> Result<()> {
+    if !credential.is_complete() {
+        anyhow::bail!("Credential has no username or password");
+    }
+    let value = GitCredential {
+        username: credential.username.clone(),
+        password: credential.password.clone(),
+        ..Default::default()
+    };
+    crate::keychain::save_password(&format!("{}{}", KEYCHAIN_PREFIX, credential.key()), &value.to_input())
+}
+
+/// Remove the keychain entry a credential was stored under, if it still holds that credential
+pub(crate) fn keychain_erase(credential: &GitCredential) -> Result<()> {
+    let stored = keychain_get(&GitCredential { password: None, ..credential.clone() });
+    let matches = stored.is_some_and(|stored| {
+        credential.password.is_none() || stored.password == credential.password
+    });
+    if matches {
+        crate::keychain::delete_password(&format!("{}{}", KEYCHAIN_PREFIX, credential.key()))?;
+    }
+    Ok(())
+}
+
+/// Serve one `get`, `store` or `erase` request from git on stdin/stdout
+///
+/// Returns the process exit code.
+pub fn run_keychain_helper(action: &str) -> i32 {
+    let mut input = String::new();
+    if std::io::stdin().read_to_string(&mut input).is_err() {
+        return 1;
+    }
+    let request = GitCredential::parse(&input);
+
+    match action {
+        "get" => {
+            if let Some(credential) = keychain_get(&request) {
+                print!("{}", credential.to_input());
+            }
+        }
+        "store" => {
+            let _ = keychain_store(&request);
+        }
+        "erase" => {
+            let _ = keychain_erase(&request);
+        }
+        // Unknown actions must be ignored so newer git versions keep working
+        _ => {}
+    }
+    0
+}
+
+/// Add or remove CaoGit as a helper in the global git configuration
+pub fn set_keychain_helper(enabled: bool) -> Result<()> {
+    let path = Config::find_global()
+        .or_else(|_| home::home_dir().map(|home| home.join(".gitconfig")).ok_or(git2::Error::from_str("No home directory")))?;
+    let mut config = Config::open(&path)?;
+    let value = keychain_helper_command()?;
+    let pattern = format!("^{}$", regex::escape(&value));
+
+    if enabled {
+        // Replaces an existing copy instead of adding a duplicate
+        config.set_multivar("credential.helper", &pattern, &value)?;
+    } else {
+        match config.remove_multivar("credential.helper", &pattern) {
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// `credential.helper` value that runs this executable as a helper
+fn keychain_helper_command() -> Result<String> {
+    let exe = std::env::current_exe().context("Failed to locate the CaoGit executable")?;
+    let exe = exe.to_string_lossy().replace('\\', "/").replace('\'', r"'\''");
+    Ok(format!("!'{}' {}", exe, HELPER_ARG))
+}
+
+pub(crate) fn keychain_helper_installed(config: &Config) -> bool {
+    let Ok(value) = keychain_helper_command() else { return false };
+    let mut found = false;
+    if let Ok(entries) = config.multivar("credential.helper", None) {
+        let _ = entries.for_each(|entry| found |= entry.value() == Some(value.as_str()));
+    }
+    found
+}
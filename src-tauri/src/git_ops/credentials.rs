@@ -0,0 +1,405 @@
+//! Credentials for network operations
+//!
+//! Every fetch, push and clone answers libgit2's credential requests through
+//! one provider. Each request that follows a rejected credential moves on to
+//! the next source: the app's `AuthConfig`, tokens stored in the keychain, the
+//! git credential helpers, the SSH agent, and the keys in `~/.ssh`.
+//!
+//! A user name and password the server rejects is erased from the credential
+//! helpers and the keychain. Once the caller reports the operation succeeded
+//! through `CredentialApproval`, the accepted one is stored there for next time.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use git2::{Config, Cred, CredentialType, RemoteCallbacks};
+
+use super::credential_helper::{
+    credential_approve, credential_fill, credential_reject, keychain_erase, keychain_get, keychain_helper_installed,
+    keychain_store, request_for, GitCredential,
+};
+use super::repository::GitRepository;
+use super::types::AuthConfig;
+
+/// Upper bound on credential requests per operation, so a rejected secret cannot loop forever
+const MAX_CREDENTIAL_ATTEMPTS: usize = 10;
+
+/// Default key files tried after those named in `~/.ssh/config`
+const DEFAULT_SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+impl GitRepository {
+    /// Credential callbacks that also consult this repository's credential helpers
+    pub(crate) fn credential_callbacks<'a>(&self, auth_config: Option<AuthConfig>) -> (RemoteCallbacks<'a>, CredentialApproval) {
+        credential_callbacks(auth_config, self.repo.config().ok())
+    }
+}
+
+/// Callbacks answering credential requests from a fresh `CredentialProvider`
+///
+/// Without `config` the global git configuration supplies the credential helpers.
+/// Call `approve` on the returned handle once the remote operation has succeeded.
+pub(crate) fn credential_callbacks<'a>(
+    auth_config: Option<AuthConfig>,
+    config: Option<Config>,
+) -> (RemoteCallbacks<'a>, CredentialApproval) {
+    let provider = Rc::new(RefCell::new(CredentialProvider::new(auth_config, config)));
+    let requests = provider.clone();
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        requests.borrow_mut().next(url, username_from_url, allowed_types)
+    });
+    (callbacks, CredentialApproval(provider))
+}
+
+/// Stores the credential an operation ended up using, once the caller knows it succeeded
+///
+/// libgit2 only asks again after a refusal, so the last user name and password handed
+/// out is the accepted one. A failed operation simply drops the handle.
+pub(crate) struct CredentialApproval(Rc<RefCell<CredentialProvider>>);
+
+impl CredentialApproval {
+    pub(crate) fn approve(self) {
+        let mut provider = self.0.borrow_mut();
+        if let Some(accepted) = provider.pending.take() {
+            provider.approve(&accepted);
+        }
+    }
+}
+
+/// A place credentials can come from, in the order they are tried
+enum Source {
+    AuthConfig,
+    Keychain,
+    CredentialHelper,
+    SshAgent,
+    SshKey(PathBuf),
+    Default,
+}
+
+impl Source {
+    fn describe(&self) -> String {
+        match self {
+            Source::AuthConfig => "configured credentials".to_string(),
+            Source::Keychain => "keychain token".to_string(),
+            Source::CredentialHelper => "git credential helper".to_string(),
+            Source::SshAgent => "SSH agent".to_string(),
+            Source::SshKey(path) => format!("SSH key {}", path.display()),
+            Source::Default => "default credentials".to_string(),
+        }
+    }
+}
+
+/// Hands out one credential per request, moving to the next source after each rejection
+struct CredentialProvider {
+    auth_config: Option<AuthConfig>,
+    config: Option<Config>,
+    sources: Option<VecDeque<Source>>, // built on the first request, once the host is known
+    attempts: usize,
+    tried: Vec<String>,
+    pending: Option<GitCredential>, // user name and password awaiting the server's verdict
+}
+
+impl CredentialProvider {
+    fn new(auth_config: Option<AuthConfig>, config: Option<Config>) -> Self {
+        Self {
+            auth_config,
+            config: config.or_else(|| Config::open_default().ok()),
+            sources: None,
+            attempts: 0,
+            tried: Vec::new(),
+            pending: None,
+        }
+    }
+
+    fn next(&mut self, url: &str, username_from_url: Option<&str>, allowed: CredentialType) -> Result<Cred, git2::Error> {
+        let username = username_from_url.unwrap_or("git");
+        // SSH asks for the user name on its own before offering any key
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+
+        // libgit2 only asks again when the previous credential was refused
+        if let Some(rejected) = self.pending.take() {
+            self.reject(&rejected);
+        }
+
+        self.attempts += 1;
+        if self.attempts > MAX_CREDENTIAL_ATTEMPTS {
+            return Err(self.exhausted(url));
+        }
+
+        let host = url_host(url);
+        let mut sources = self.sources.take()
+            .unwrap_or_else(|| credential_sources(host.as_deref()));
+        while let Some(source) = sources.pop_front() {
+            if let Some(cred) = self.credential_from(&source, url, host.as_deref(), username_from_url, allowed) {
+                self.tried.push(source.describe());
+                self.sources = Some(sources);
+                return Ok(cred);
+            }
+        }
+        self.sources = Some(sources);
+        Err(self.exhausted(url))
+    }
+
+    fn credential_from(
+        &mut self,
+        source: &Source,
+        url: &str,
+        host: Option<&str>,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Option<Cred> {
+        let username = username_from_url.unwrap_or("git");
+        match source {
+            Source::AuthConfig if allowed.is_user_pass_plaintext() => {
+                let auth = self.auth_config.as_ref()?;
+                let secret = match auth.auth_type.as_str() {
+                    "token" => auth.token.as_deref(),
+                    "password" => auth.password.as_deref(),
+                    _ => None,
+                }?;
+                let username = auth.username.as_deref().filter(|u| !u.is_empty()).unwrap_or(username);
+                let credential = GitCredential {
+                    username: Some(username.to_string()),
+                    password: Some(secret.to_string()),
+                    ..self.request(url)
+                };
+                self.userpass(credential)
+            }
+            Source::Keychain if allowed.is_user_pass_plaintext() => {
+                let request = GitCredential { username: username_from_url.map(String::from), ..self.request(url) };
+                let credential = match keychain_get(&request) {
+                    Some(credential) => credential,
+                    None => GitCredential {
+                        username: Some(username.to_string()),
+                        password: Some(crate::keychain::get_password(keychain_account(host?)?).ok()?),
+                        ..request
+                    },
+                };
+                self.userpass(credential)
+            }
+            Source::CredentialHelper if allowed.is_user_pass_plaintext() => {
+                let credential = credential_fill(self.config.as_ref()?, url, username_from_url)?;
+                self.userpass(credential)
+            }
+            Source::SshAgent if allowed.is_ssh_key() => Cred::ssh_key_from_agent(username).ok(),
+            Source::SshKey(private_key) if allowed.is_ssh_key() => {
+                let public_key = PathBuf::from(format!("{}.pub", private_key.display()));
+                let public_key = public_key.is_file().then_some(public_key.as_path());
+                Cred::ssh_key(username, public_key, private_key, None).ok()
+            }
+            Source::Default if allowed.contains(CredentialType::DEFAULT) => Cred::default().ok(),
+            _ => None,
+        }
+    }
+
+    /// Hand out a user name and password, remembering it until the server answers
+    fn userpass(&mut self, credential: GitCredential) -> Option<Cred> {
+        let cred = Cred::userpass_plaintext(credential.username.as_deref()?, credential.password.as_deref()?).ok()?;
+        self.pending = Some(credential);
+        Some(cred)
+    }
+
+    /// What the credential helpers see for `url`
+    fn request(&self, url: &str) -> GitCredential {
+        match &self.config {
+            Some(config) => request_for(config, url),
+            None => GitCredential::from_url(url),
+        }
+    }
+
+    /// Store an accepted credential with the helpers, and in the keychain unless
+    /// CaoGit's own helper is one of them and has already done so
+    fn approve(&self, credential: &GitCredential) {
+        if let Some(config) = &self.config {
+            credential_approve(config, credential);
+        }
+        if self.keychain_through_helper() {
+            return;
+        }
+        let request = GitCredential { password: None, ..credential.clone() };
+        if keychain_get(&request).is_none_or(|stored| stored.password != credential.password) {
+            let _ = keychain_store(credential);
+        }
+    }
+
+    fn reject(&self, credential: &GitCredential) {
+        if let Some(config) = &self.config {
+            credential_reject(config, credential);
+        }
+        if !self.keychain_through_helper() {
+            let _ = keychain_erase(credential);
+        }
+    }
+
+    fn keychain_through_helper(&self) -> bool {
+        self.config.as_ref().is_some_and(keychain_helper_installed)
+    }
+
+    fn exhausted(&self, url: &str) -> git2::Error {
+        let message = if self.tried.is_empty() {
+            format!(
+                "No credentials available for {}. Configure a token, a git credential helper or an SSH key.",
+                url
+            )
+        } else {
+            format!("Authentication failed for {} (tried {})", url, self.tried.join(", "))
+        };
+        git2::Error::from_str(&message)
+    }
+}
+
+fn credential_sources(host: Option<&str>) -> VecDeque<Source> {
+    let mut sources = VecDeque::from([
+        Source::AuthConfig,
+        Source::Keychain,
+        Source::CredentialHelper,
+        Source::SshAgent,
+    ]);
+    sources.extend(ssh_key_files(host).into_iter().map(Source::SshKey));
+    sources.push_back(Source::Default);
+    sources
+}
+
+/// Keychain account holding the token for a hosting service
+fn keychain_account(host: &str) -> Option<&'static str> {
+    match host {
+        "github.com" => Some("github_token"),
+        "gitlab.com" => Some("gitlab_token"),
+        "gitee.com" => Some("gitee_token"),
+        _ => None,
+    }
+}
+
+/// Host of an URL, including scp-like `user@host:path` remotes
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = rest.split('/').next()?;
+    let host_port = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+    let host = host_port.split(':').next()?.to_ascii_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// `IdentityFile`s that `~/.ssh/config` gives for `host`, then the default keys
+fn ssh_key_files(host: Option<&str>) -> Vec<PathBuf> {
+    let Some(home) = home::home_dir() else { return Vec::new() };
+    let ssh_dir = home.join(".ssh");
+    let mut keys = Vec::new();
+
+    if let (Some(host), Ok(config)) = (host, fs::read_to_string(ssh_dir.join("config"))) {
+        // Options before the first Host line apply to every host
+        let mut applies = true;
+        for line in config.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((keyword, value)) = line.split_once(|c: char| c.is_whitespace() || c == '=') else { continue };
+            let value = value.trim_start_matches(|c: char| c.is_whitespace() || c == '=').trim();
+            match keyword.to_ascii_lowercase().as_str() {
+                "host" => applies = host_matches(value, host),
+                "match" => applies = false,
+                "identityfile" if applies => keys.push(expand_home(value.trim_matches('"'), &home)),
+                _ => {}
+            }
+        }
+    }
+
+    for name in DEFAULT_SSH_KEYS {
+        let path = ssh_dir.join(name);
+        if !keys.contains(&path) {
+            keys.push(path);
+        }
+    }
+    keys.retain(|path| path.is_file());
+    keys
+}
+
+/// Whether a `Host` line's patterns select `host`; a matching `!pattern` excludes it
+fn host_matches(patterns: &str, host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        let pattern = pattern.to_ascii_lowercase();
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if glob_match(negated.as_bytes(), host.as_bytes()) {
+                return false;
+            }
+        } else if glob_match(pattern.as_bytes(), host.as_bytes()) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+/// Match `*` and `?` wildcards as ssh_config does
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => glob_match(&pattern[1..], text) || (!text.is_empty() && glob_match(pattern, &text[1..])),
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => glob_match(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+fn expand_home(path: &str, home: &Path) -> PathBuf {
+    match path.strip_prefix("~/").or_else(|| path.strip_prefix("%d/")) {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("https://GitHub.com/a/b.git").as_deref(), Some("github.com"));
+        assert_eq!(url_host("https://user@example.com:8443/repo").as_deref(), Some("example.com"));
+        assert_eq!(url_host("git@gitee.com:owner/repo.git").as_deref(), Some("gitee.com"));
+        assert_eq!(url_host("ssh://git@host/repo").as_deref(), Some("host"));
+        assert_eq!(url_host(""), None);
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("github.com", "github.com"));
+        assert!(host_matches("*.example.com other", "git.example.com"));
+        assert!(host_matches("gi?hub.com", "github.com"));
+        assert!(!host_matches("*.example.com", "example.com"));
+        assert!(!host_matches("* !github.com", "github.com"));
+        assert!(host_matches("* !github.com", "gitlab.com"));
+    }
+
+    #[test]
+    fn test_keychain_account() {
+        assert_eq!(keychain_account("github.com"), Some("github_token"));
+        assert_eq!(keychain_account("example.com"), None);
+    }
+
+    #[test]
+    fn test_auth_config_offered_once() {
+        let auth = AuthConfig {
+            auth_type: "token".to_string(),
+            token: Some("secret".to_string()),
+            username: Some("me".to_string()),
+            password: None,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::open(&dir.path().join("config")).unwrap();
+        let mut provider = CredentialProvider::new(Some(auth), Some(config));
+        let url = "https://example.invalid/repo.git";
+
+        assert!(provider.next(url, None, CredentialType::USER_PASS_PLAINTEXT).is_ok());
+        assert_eq!(provider.pending.as_ref().and_then(|c| c.password.as_deref()), Some("secret"));
+        assert_eq!(provider.tried, vec!["configured credentials".to_string()]);
+        // A second request means the token was refused; nothing else is left for plain HTTP auth here
+        let _ = provider.next(url, None, CredentialType::USER_PASS_PLAINTEXT);
+        assert!(!provider.tried.iter().skip(1).any(|source| source == "configured credentials"));
+    }
+}
@@ -2,15 +2,118 @@
 //!
 //! This module handles all diff-related Git operations.
 
-use git2::{DiffOptions, Status, StatusOptions};
+use std::path::Path;
+use git2::{
+    AttrCheckFlags, AttrValue, Delta, Diff, DiffDelta, DiffFile, DiffFindOptions, DiffOptions,
+    Patch, Status, StatusOptions, Tree,
+};
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 
+use super::inline_diff::{highlight_hunk, InlineMode};
+use super::lfs::{parse_lfs_pointer, MAX_LFS_POINTER_BYTES};
 use super::repository::GitRepository;
-use super::types::{DiffResult, DiffHunk, DiffLine};
+use super::types::{BinaryDiff, DiffResult, DiffHunk, DiffLine, DiffSettings, LfsDiff, LfsPointer};
+
+/// Maximum number of diff lines returned per file; the rest is cut off
+const MAX_DIFF_LINES: usize = 10_000;
+/// Untracked files are only read up to this size
+const MAX_NEW_FILE_BYTES: u64 = 8 * 1024 * 1024;
+/// Tracked files larger than this get a size summary instead of a line diff
+const MAX_DIFF_FILE_BYTES: u64 = 8 * 1024 * 1024;
+/// Images larger than this are summarized without their content
+const MAX_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Revspec token for the index in `diff_revisions`
+const DIFF_INDEX: &str = "INDEX";
+/// Revspec token for the working directory in `diff_revisions`
+const DIFF_WORKDIR: &str = "WORKDIR";
+
+/// One side of a diff
+enum DiffSide<'r> {
+    /// A commit or tree; `None` is the empty tree
+    Tree(Option<Tree<'r>>),
+    Index,
+    Workdir,
+}
 
 impl GitRepository {
+    /// Diff two revisions, returning every changed file with its hunks
+    ///
+    /// `from` and `to` are revspecs (`main`, `HEAD~2`, `stash@{0}^1`, ...), the tokens
+    /// `INDEX` or `WORKDIR`, or an empty string for the empty tree. `paths` limits the
+    /// diff to the given pathspecs.
+    pub fn diff_revisions(
+        &self,
+        from: &str,
+        to: &str,
+        paths: &[String],
+        settings: &DiffSettings,
+    ) -> Result<Vec<DiffResult>> {
+        let mut diff_opts = diff_options(settings)?;
+        for path in paths {
+            diff_opts.pathspec(path);
+        }
+
+        let from = self.diff_side(from)?;
+        let to = self.diff_side(to)?;
+
+        let mut diff = match (&from, &to) {
+            (DiffSide::Tree(old), DiffSide::Tree(new)) => {
+                self.repo.diff_tree_to_tree(old.as_ref(), new.as_ref(), Some(&mut diff_opts))?
+            }
+            (DiffSide::Tree(tree), DiffSide::Index) => {
+                self.repo.diff_tree_to_index(tree.as_ref(), None, Some(&mut diff_opts))?
+            }
+            (DiffSide::Tree(tree), DiffSide::Workdir) => {
+                self.repo.diff_tree_to_workdir_with_index(tree.as_ref(), Some(&mut diff_opts))?
+            }
+            (DiffSide::Index, DiffSide::Workdir) => {
+                self.repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
+            }
+            // The opposite directions are the same diffs reversed
+            (DiffSide::Index, DiffSide::Tree(tree)) => {
+                diff_opts.reverse(true);
+                self.repo.diff_tree_to_index(tree.as_ref(), None, Some(&mut diff_opts))?
+            }
+            (DiffSide::Workdir, DiffSide::Tree(tree)) => {
+                diff_opts.reverse(true);
+                self.repo.diff_tree_to_workdir_with_index(tree.as_ref(), Some(&mut diff_opts))?
+            }
+            (DiffSide::Workdir, DiffSide::Index) => {
+                diff_opts.reverse(true);
+                self.repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
+            }
+            (DiffSide::Index, DiffSide::Index) | (DiffSide::Workdir, DiffSide::Workdir) => {
+                return Ok(Vec::new());
+            }
+        };
+
+        find_similar(&mut diff, settings)?;
+        self.diff_to_results(&diff, settings)
+    }
+
+    /// Resolve one side of `diff_revisions`
+    fn diff_side(&self, spec: &str) -> Result<DiffSide<'_>> {
+        match spec.trim() {
+            DIFF_INDEX => Ok(DiffSide::Index),
+            DIFF_WORKDIR => Ok(DiffSide::Workdir),
+            "" => Ok(DiffSide::Tree(None)),
+            spec => {
+                let tree = self.repo.revparse_single(spec)
+                    .context(format!("无法找到引用: {}", spec))?
+                    .peel_to_tree()
+                    .context(format!("引用 {} 不是有效的提交", spec))?;
+                Ok(DiffSide::Tree(Some(tree)))
+            }
+        }
+    }
+
     /// Get the diff for a specific file
-    pub fn get_file_diff(&self, file_path: &str, staged: bool) -> Result<DiffResult> {
+    ///
+    /// Hunk indices used by `stage_hunk` and friends refer to the diff computed with
+    /// the same settings.
+    pub fn get_file_diff(&self, file_path: &str, staged: bool, settings: &DiffSettings) -> Result<DiffResult> {
         // Check if this is a new file (untracked)
         let mut status_opts = StatusOptions::new();
         status_opts.include_untracked(true);
@@ -27,12 +130,13 @@ impl GitRepository {
         }
 
         // Original logic for non-new files
-        self.get_existing_file_diff(file_path, staged)
+        self.get_existing_file_diff(file_path, staged, settings)
     }
 
     /// Get diff for a new (untracked) file
     fn get_new_file_diff(&self, file_path: &str) -> Result<DiffResult> {
         use std::fs;
+        use std::io::Read;
 
         // Safely get repository working directory
         let workdir = self.repo.workdir()
@@ -50,105 +154,408 @@ impl GitRepository {
             anyhow::bail!("File path is outside repository: {}", file_path);
         }
 
-        let content = fs::read_to_string(&file_full_path)
+        let size = fs::metadata(&file_full_path)
+            .context(format!("Failed to read file: {}", file_path))?
+            .len();
+        let mut content = Vec::new();
+        fs::File::open(&file_full_path)
+            .and_then(|file| file.take(MAX_NEW_FILE_BYTES).read_to_end(&mut content))
             .context(format!("Failed to read file: {}", file_path))?;
 
-        let lines: Vec<&str> = content.lines().collect();
-        let total_lines = lines.len() as u32;
+        let mut result = DiffResult {
+            old_path: "/dev/null".to_string(),
+            new_path: file_path.to_string(),
+            status: "Added".to_string(),
+            hunks: Vec::new(),
+            binary: None,
+            truncated: false,
+            lfs: None,
+        };
+
+        let binary = match self.attr_binary(file_path) {
+            Some(binary) => binary,
+            // Same heuristic as git: a NUL byte in the first 8000 bytes
+            None => content.iter().take(8000).any(|b| *b == 0),
+        };
+        if binary {
+            let mut info = BinaryDiff {
+                old_size: None,
+                new_size: Some(size),
+                mime_type: image_mime_type(file_path).map(|m| m.to_string()),
+                old_content: None,
+                new_content: None,
+            };
+            if info.mime_type.is_some() && size <= MAX_IMAGE_BYTES {
+                info.new_content = fs::read(&file_full_path).ok().map(|data| BASE64.encode(data));
+            }
+            result.binary = Some(info);
+            return Ok(result);
+        }
 
+        let content = String::from_utf8_lossy(&content);
         let mut diff_lines = Vec::new();
-        for (i, line) in lines.iter().enumerate() {
+        for (i, line) in content.lines().enumerate() {
+            if i >= MAX_DIFF_LINES {
+                result.truncated = true;
+                break;
+            }
             diff_lines.push(DiffLine {
                 origin: '+',
                 content: line.to_string() + "\n",
                 old_lineno: None,
                 new_lineno: Some((i + 1) as u32),
+                spans: Vec::new(),
             });
         }
+        if size > MAX_NEW_FILE_BYTES {
+            result.truncated = true;
+        }
 
-        let hunk = DiffHunk {
+        let total_lines = diff_lines.len() as u32;
+        result.hunks.push(DiffHunk {
             old_start: 0,
             old_lines: 0,
             new_start: 1,
             new_lines: total_lines,
             header: format!("@@ -0,0 +1,{} @@ New file", total_lines),
             lines: diff_lines,
-        };
+        });
 
-        Ok(DiffResult {
-            old_path: "/dev/null".to_string(),
-            new_path: file_path.to_string(),
-            status: "Added".to_string(),
-            hunks: vec![hunk],
-        })
+        Ok(result)
     }
 
     /// Get diff for an existing file
-    fn get_existing_file_diff(&self, file_path: &str, staged: bool) -> Result<DiffResult> {
-        use std::cell::RefCell;
-        use std::rc::Rc;
-
-        let mut diff_opts = DiffOptions::new();
+    fn get_existing_file_diff(&self, file_path: &str, staged: bool, settings: &DiffSettings) -> Result<DiffResult> {
+        let mut diff_opts = diff_options(settings)?;
         diff_opts.pathspec(file_path);
 
-        let diff = if staged {
+        let mut diff = if staged {
             let head = self.repo.head()?.peel_to_tree()?;
             self.repo.diff_tree_to_index(Some(&head), None, Some(&mut diff_opts))?
         } else {
             self.repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
         };
+        find_similar(&mut diff, settings)?;
 
-        let mut result = DiffResult {
-            old_path: file_path.to_string(),
-            new_path: file_path.to_string(),
-            status: "Modified".to_string(),
-            hunks: Vec::new(),
-        };
+        let result = self.diff_to_results(&diff, settings)?
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| DiffResult {
+                old_path: file_path.to_string(),
+                new_path: file_path.to_string(),
+                status: "Modified".to_string(),
+                hunks: Vec::new(),
+                binary: None,
+                truncated: false,
+                lfs: None,
+            });
+        Ok(result)
+    }
+
+    /// Convert every file of a diff into a `DiffResult` with its hunks and lines
+    ///
+    /// Binary files get a size summary (and base64 content for images) instead of
+    /// hunks, as do files over `MAX_DIFF_FILE_BYTES` (marked truncated). Text diffs
+    /// stop after `MAX_DIFF_LINES` lines, and paired removed/added lines get
+    /// intra-line change spans.
+    fn diff_to_results(&self, diff: &Diff, settings: &DiffSettings) -> Result<Vec<DiffResult>> {
+        let inline_mode = InlineMode::from_setting(settings.inline_diff.as_deref())?;
+        let mut results = Vec::new();
 
-        let hunks_ref = Rc::new(RefCell::new(Vec::new()));
-        let hunks_clone = Rc::clone(&hunks_ref);
+        for idx in 0..diff.deltas().len() {
+            let Some(delta) = diff.get_delta(idx) else {
+                continue;
+            };
+            // Building a patch loads both sides into memory, so huge files are only summarized
+            let oversized = [delta.old_file(), delta.new_file()]
+                .iter()
+                .any(|file| self.diff_file_size(file).is_some_and(|size| size > MAX_DIFF_FILE_BYTES));
+            let patch = if oversized { None } else { Patch::from_diff(diff, idx)? };
+            if patch.is_none() && delta.status() == Delta::Unmodified {
+                continue;
+            }
 
-        diff.foreach(
-            &mut |delta, _progress| {
-                result.old_path = delta.old_file().path()
+            let mut result = DiffResult {
+                old_path: delta.old_file().path()
                     .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|| "/dev/null".to_string());
-                result.new_path = delta.new_file().path()
+                    .unwrap_or_else(|| "/dev/null".to_string()),
+                new_path: delta.new_file().path()
                     .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|| file_path.to_string());
-                result.status = format!("{:?}", delta.status());
-                true
-            },
-            None,
-            Some(&mut |_delta, hunk| {
-                hunks_clone.borrow_mut().push(DiffHunk {
-                    old_start: hunk.old_start(),
-                    old_lines: hunk.old_lines(),
-                    new_start: hunk.new_start(),
-                    new_lines: hunk.new_lines(),
-                    header: String::from_utf8_lossy(hunk.header()).to_string(),
-                    lines: Vec::new(),
-                });
-                true
-            }),
-            Some(&mut |_delta, _hunk, line| {
-                let mut hunks = hunks_clone.borrow_mut();
-                if let Some(last_hunk) = hunks.last_mut() {
-                    last_hunk.lines.push(DiffLine {
+                    .unwrap_or_else(|| "/dev/null".to_string()),
+                status: format!("{:?}", delta.status()),
+                hunks: Vec::new(),
+                binary: None,
+                truncated: false,
+                lfs: None,
+            };
+
+            // LFS files are compared by their pointers; the pointer text itself is noise
+            if let Some(lfs) = self.lfs_diff(&delta) {
+                result.binary = Some(self.lfs_binary_diff(&delta, &lfs));
+                result.lfs = Some(lfs);
+                results.push(result);
+                continue;
+            }
+
+            // libgit2 honours .gitattributes (`binary`, `-diff`) when flagging binary files
+            let patch = match patch {
+                Some(patch) if !patch.delta().flags().is_binary() => patch,
+                _ => {
+                    result.truncated = oversized;
+                    result.binary = Some(self.binary_diff(&delta));
+                    results.push(result);
+                    continue;
+                }
+            };
+
+            let mut line_total = 0;
+            'hunks: for hunk_idx in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(hunk_idx)?;
+                let mut lines = Vec::new();
+                for line_idx in 0..line_count {
+                    if line_total >= MAX_DIFF_LINES {
+                        result.truncated = true;
+                        break;
+                    }
+                    let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                    lines.push(DiffLine {
                         origin: line.origin(),
                         content: String::from_utf8_lossy(line.content()).to_string(),
                         old_lineno: line.old_lineno(),
                         new_lineno: line.new_lineno(),
+                        spans: Vec::new(),
                     });
+                    line_total += 1;
                 }
-                true
-            }),
-        )?;
-
-        drop(hunks_clone);
-        result.hunks = Rc::try_unwrap(hunks_ref)
-            .map_err(|_| git2::Error::from_str("Failed to unwrap hunks reference"))?
-            .into_inner();
-        Ok(result)
+                let mut diff_hunk = DiffHunk {
+                    old_start: hunk.old_start(),
+                    old_lines: hunk.old_lines(),
+                    new_start: hunk.new_start(),
+                    new_lines: hunk.new_lines(),
+                    header: String::from_utf8_lossy(hunk.header()).to_string(),
+                    lines,
+                };
+                if let Some(mode) = inline_mode {
+                    highlight_hunk(&mut diff_hunk, mode);
+                }
+                result.hunks.push(diff_hunk);
+                if result.truncated {
+                    break 'hunks;
+                }
+            }
+
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// Size summary of a binary change, with base64 content when it is a small image
+    fn binary_diff(&self, delta: &DiffDelta) -> BinaryDiff {
+        let old_exists = !matches!(delta.status(), Delta::Added | Delta::Untracked);
+        let new_exists = delta.status() != Delta::Deleted;
+        let path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mime_type = image_mime_type(&path);
+
+        let side = |file: DiffFile, exists: bool| -> (Option<u64>, Option<String>) {
+            if !exists {
+                return (None, None);
+            }
+            let size = self.diff_file_size(&file);
+            let content = match (mime_type, size) {
+                (Some(_), Some(size)) if size <= MAX_IMAGE_BYTES => {
+                    self.diff_file_content(&file).map(|data| BASE64.encode(data))
+                }
+                _ => None,
+            };
+            (size, content)
+        };
+        let (old_size, old_content) = side(delta.old_file(), old_exists);
+        let (new_size, new_content) = side(delta.new_file(), new_exists);
+
+        BinaryDiff {
+            old_size,
+            new_size,
+            mime_type: mime_type.map(|m| m.to_string()),
+            old_content,
+            new_content,
+        }
+    }
+
+    /// Pointers on both sides of a change to an LFS-tracked file
+    ///
+    /// Returns `None` when neither side is a pointer, e.g. for files committed before LFS was set up.
+    fn lfs_diff(&self, delta: &DiffDelta) -> Option<LfsDiff> {
+        let path = delta.new_file().path().or_else(|| delta.old_file().path())?;
+        if !self.is_lfs_path(&path.to_string_lossy()) {
+            return None;
+        }
+
+        let pointer = |file: DiffFile| -> Option<LfsPointer> {
+            if self.diff_file_size(&file)? > MAX_LFS_POINTER_BYTES {
+                return None;
+            }
+            let (oid, size) = parse_lfs_pointer(&self.diff_file_content(&file)?)?;
+            Some(self.lfs_pointer(oid, size))
+        };
+        let old = match delta.status() {
+            Delta::Added | Delta::Untracked => None,
+            _ => pointer(delta.old_file()),
+        };
+        let new = match delta.status() {
+            Delta::Deleted => None,
+            _ => pointer(delta.new_file()),
+        };
+
+        (old.is_some() || new.is_some()).then_some(LfsDiff { old, new })
+    }
+
+    /// Size summary of an LFS change using the real objects behind the pointers
+    fn lfs_binary_diff(&self, delta: &DiffDelta, lfs: &LfsDiff) -> BinaryDiff {
+        let path = delta.new_file().path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mime_type = image_mime_type(&path);
+
+        // Content comes from the local LFS store, or from the working directory once smudged
+        let side = |pointer: Option<&LfsPointer>, file: DiffFile, exists: bool| -> (Option<u64>, Option<String>) {
+            if !exists {
+                return (None, None);
+            }
+            let (size, source) = match pointer {
+                Some(pointer) => (Some(pointer.size), pointer.downloaded.then(|| self.lfs_object_path(&pointer.oid))),
+                None => {
+                    let full_path = self.repo.workdir().zip(file.path()).map(|(dir, p)| dir.join(p));
+                    (full_path.as_ref().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()), full_path)
+                }
+            };
+            let content = match (mime_type, size, source) {
+                (Some(_), Some(size), Some(source)) if size <= MAX_IMAGE_BYTES => {
+                    std::fs::read(source).ok().map(|data| BASE64.encode(data))
+                }
+                _ => None,
+            };
+            (size, content)
+        };
+        let (old_size, old_content) = side(
+            lfs.old.as_ref(),
+            delta.old_file(),
+            !matches!(delta.status(), Delta::Added | Delta::Untracked),
+        );
+        let (new_size, new_content) = side(lfs.new.as_ref(), delta.new_file(), delta.status() != Delta::Deleted);
+
+        BinaryDiff {
+            old_size,
+            new_size,
+            mime_type: mime_type.map(|m| m.to_string()),
+            old_content,
+            new_content,
+        }
     }
+
+    /// Size of one side of a delta, from the object database or the working directory
+    fn diff_file_size(&self, file: &DiffFile) -> Option<u64> {
+        if !file.id().is_zero() {
+            if let Ok((size, _)) = self.repo.odb().and_then(|odb| odb.read_header(file.id())) {
+                return Some(size as u64);
+            }
+        }
+        let path = self.repo.workdir()?.join(file.path()?);
+        std::fs::metadata(path).ok().map(|m| m.len())
+    }
+
+    /// Content of one side of a delta, from the object database or the working directory
+    fn diff_file_content(&self, file: &DiffFile) -> Option<Vec<u8>> {
+        if !file.id().is_zero() {
+            if let Ok(blob) = self.repo.find_blob(file.id()) {
+                return Some(blob.content().to_vec());
+            }
+        }
+        let path = self.repo.workdir()?.join(file.path()?);
+        std::fs::read(path).ok()
+    }
+
+    /// Whether .gitattributes marks a path binary (`Some(true)`) or forces a text diff (`Some(false)`)
+    fn attr_binary(&self, path: &str) -> Option<bool> {
+        let value = self.repo
+            .get_attr(Path::new(path), "diff", AttrCheckFlags::FILE_THEN_INDEX)
+            .ok()?;
+        match AttrValue::from_string(value) {
+            AttrValue::False => Some(true),
+            AttrValue::True | AttrValue::String(_) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Build `DiffOptions` from the user's diff settings
+pub(super) fn diff_options(settings: &DiffSettings) -> Result<DiffOptions> {
+    let mut opts = DiffOptions::new();
+
+    match settings.whitespace.as_deref() {
+        None | Some("") | Some("none") => {}
+        Some("all") => { opts.ignore_whitespace(true); }
+        Some("change") => { opts.ignore_whitespace_change(true); }
+        Some("eol") => { opts.ignore_whitespace_eol(true); }
+        Some(other) => anyhow::bail!("Unknown whitespace mode: {}", other),
+    }
+
+    if let Some(lines) = settings.context_lines {
+        opts.context_lines(lines);
+    }
+    if let Some(lines) = settings.interhunk_lines {
+        opts.interhunk_lines(lines);
+    }
+
+    match settings.algorithm.as_deref() {
+        None | Some("") | Some("myers") | Some("default") => {}
+        Some("minimal") => { opts.minimal(true); }
+        Some("patience") => { opts.patience(true); }
+        // libgit2 has no histogram implementation
+        Some("histogram") => anyhow::bail!("Unsupported diff algorithm: histogram"),
+        Some(other) => anyhow::bail!("Unknown diff algorithm: {}", other),
+    }
+
+    Ok(opts)
+}
+
+/// Run rename/copy detection on a diff when the settings ask for it
+fn find_similar(diff: &mut Diff, settings: &DiffSettings) -> Result<()> {
+    if !settings.detect_renames && !settings.detect_copies {
+        return Ok(());
+    }
+
+    let mut find_opts = DiffFindOptions::new();
+    find_opts
+        .renames(settings.detect_renames || settings.detect_copies)
+        .copies(settings.detect_copies);
+    if let Some(threshold) = settings.rename_threshold {
+        find_opts.rename_threshold(threshold.min(100));
+    }
+    if let Some(threshold) = settings.copy_threshold {
+        find_opts.copy_threshold(threshold.min(100));
+    }
+
+    diff.find_similar(Some(&mut find_opts))?;
+    Ok(())
+}
+
+/// MIME type of image formats the frontend can show side by side
+fn image_mime_type(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        _ => return None,
+    };
+    Some(mime)
 }
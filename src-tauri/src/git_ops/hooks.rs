@@ -0,0 +1,299 @@
+//! Git hook execution
+//!
+//! libgit2 never runs hooks, so this module runs the standard client-side
+//! hooks (pre-commit, prepare-commit-msg, commit-msg, post-commit, pre-push,
+//! post-merge) itself. Hooks are looked up in `core.hooksPath` or the `hooks`
+//! directory of the repository, their output is streamed to the frontend as
+//! `git-hook-output` events, and a non-zero exit aborts the operation.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use anyhow::{Context, Result};
+use tauri::Emitter;
+
+use super::repository::GitRepository;
+use super::signing::expand_home;
+use super::types::HookOutput;
+
+/// Maximum number of output lines quoted in a hook failure message
+const HOOK_ERROR_LINES: usize = 20;
+
+/// Per-operation hook settings
+#[derive(Clone, Default)]
+pub struct HookOptions {
+    /// Skip the verification hooks (pre-commit, commit-msg, pre-push), like `--no-verify`
+    pub no_verify: bool,
+    /// Window receiving `git-hook-output` events
+    pub window: Option<tauri::Window>,
+}
+
+impl GitRepository {
+    /// Use the given hook settings for subsequent operations
+    pub fn with_hooks(mut self, hooks: HookOptions) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Directory hooks are loaded from, honouring `core.hooksPath`
+    fn hooks_dir(&self) -> PathBuf {
+        if let Ok(path) = self.repo.config().and_then(|c| c.get_string("core.hooksPath")) {
+            let path = expand_home(&path);
+            if path.is_absolute() {
+                return path;
+            }
+            // Relative paths are resolved against the top of the working tree, as git does
+            let base = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+            return base.join(path);
+        }
+
+        // Linked worktrees share the hooks of the main repository
+        let git_dir = self.repo.path();
+        let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+            .map(|dir| git_dir.join(dir.trim()))
+            .unwrap_or_else(|_| git_dir.to_path_buf());
+        common_dir.join("hooks")
+    }
+
+    /// Find an executable hook by name
+    fn find_hook(&self, name: &str) -> Option<PathBuf> {
+        let path = self.hooks_dir().join(name);
+        if !path.is_file() {
+            return None;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = path.metadata().ok()?.permissions().mode();
+            if mode & 0o111 == 0 {
+                eprintln!("⚠️  The '{}' hook was ignored because it is not executable", name);
+                return None;
+            }
+        }
+
+        Some(path)
+    }
+
+    /// Run a hook if it exists, streaming its output
+    ///
+    /// Returns `Ok(false)` when the hook is not installed and an error when it exits non-zero.
+    pub fn run_hook(&self, name: &str, args: &[&str], stdin: Option<&[u8]>) -> Result<bool> {
+        let Some(hook_path) = self.find_hook(name) else {
+            return Ok(false);
+        };
+
+        #[cfg(windows)]
+        let mut command = {
+            // Hooks are shell scripts; Git for Windows runs them through its sh
+            let mut command = Command::new("sh");
+            command.arg(&hook_path);
+            command
+        };
+        #[cfg(not(windows))]
+        let mut command = Command::new(&hook_path);
+
+        let workdir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        command
+            .args(args)
+            .current_dir(workdir)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        if name.contains("commit") {
+            command
+                .env("GIT_EDITOR", ":")
+                .env("GIT_INDEX_FILE", self.repo.path().join("index"));
+        }
+
+        // Apps launched from Finder miss the shell PATH that husky/lefthook rely on
+        if let Some(path) = std::env::var_os("PATH") {
+            let mut paths: Vec<PathBuf> = std::env::split_paths(&path).collect();
+            for extra in ["/opt/homebrew/bin", "/usr/local/bin"] {
+                let extra = PathBuf::from(extra);
+                if !paths.contains(&extra) {
+                    paths.push(extra);
+                }
+            }
+            if let Ok(joined) = std::env::join_paths(paths) {
+                command.env("PATH", joined);
+            }
+        }
+
+        eprintln!("🪝 Running {} hook: {}", name, hook_path.display());
+        let mut child = command.spawn()
+            .context(format!("Failed to run {} hook", name))?;
+
+        let writer = match (stdin, child.stdin.take()) {
+            (Some(input), Some(mut child_stdin)) => {
+                let input = input.to_vec();
+                // A hook may exit without reading its input; that is not an error
+                Some(std::thread::spawn(move || {
+                    let _ = child_stdin.write_all(&input);
+                }))
+            }
+            _ => None,
+        };
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(self.spawn_hook_reader(name, "stdout", stdout, Arc::clone(&output)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(self.spawn_hook_reader(name, "stderr", stderr, Arc::clone(&output)));
+        }
+        for reader in readers.into_iter().chain(writer) {
+            let _ = reader.join();
+        }
+
+        let status = child.wait().context(format!("Failed to wait for {} hook", name))?;
+        if !status.success() {
+            let lines = output.lock().map(|lines| lines.clone()).unwrap_or_default();
+            let tail = lines[lines.len().saturating_sub(HOOK_ERROR_LINES)..].join("\n");
+            let code = status.code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "signal".to_string());
+            anyhow::bail!("{} hook failed (exit code {})\n{}", name, code, tail);
+        }
+
+        Ok(true)
+    }
+
+    /// Forward each line of a hook output stream to the frontend
+    fn spawn_hook_reader<R: std::io::Read + Send + 'static>(
+        &self,
+        hook: &str,
+        stream: &'static str,
+        source: R,
+        output: Arc<Mutex<Vec<String>>>,
+    ) -> std::thread::JoinHandle<()> {
+        let hook = hook.to_string();
+        let window = self.hooks.window.clone();
+
+        std::thread::spawn(move || {
+            for line in BufReader::new(source).lines() {
+                let Ok(line) = line else { break };
+                if let Some(window) = &window {
+                    let _ = window.emit("git-hook-output", HookOutput {
+                        hook: hook.clone(),
+                        stream: stream.to_string(),
+                        line: line.clone(),
+                    });
+                }
+                if let Ok(mut lines) = output.lock() {
+                    lines.push(line);
+                }
+            }
+        })
+    }
+
+    /// Run a hook whose failure cannot undo the operation (post-commit, post-merge)
+    pub(crate) fn run_post_hook(&self, name: &str, args: &[&str]) {
+        if let Err(e) = self.run_hook(name, args, None) {
+            eprintln!("⚠️  {}", e);
+        }
+    }
+
+    /// Run pre-commit, prepare-commit-msg and commit-msg, returning the final message
+    ///
+    /// `source` and `source_commit` are passed to prepare-commit-msg as git does
+    /// (`"message"` for `-m`, `"commit"` plus the HEAD id for amend).
+    pub(crate) fn run_commit_hooks(&self, message: &str, source: &str, source_commit: Option<&str>) -> Result<String> {
+        if !self.hooks.no_verify {
+            self.run_hook("pre-commit", &[], None)?;
+        }
+
+        let has_msg_hooks = self.find_hook("prepare-commit-msg").is_some()
+            || (!self.hooks.no_verify && self.find_hook("commit-msg").is_some());
+        if !has_msg_hooks {
+            return Ok(message.to_string());
+        }
+
+        let msg_file = self.repo.path().join("COMMIT_EDITMSG");
+        let mut content = message.to_string();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        std::fs::write(&msg_file, content)
+            .context("Failed to write COMMIT_EDITMSG")?;
+        let msg_path = msg_file.to_string_lossy().to_string();
+
+        let mut args = vec![msg_path.as_str(), source];
+        if let Some(commit) = source_commit {
+            args.push(commit);
+        }
+        self.run_hook("prepare-commit-msg", &args, None)?;
+        if !self.hooks.no_verify {
+            self.run_hook("commit-msg", &[msg_path.as_str()], None)?;
+        }
+
+        let edited = std::fs::read_to_string(&msg_file)
+            .context("Failed to read COMMIT_EDITMSG")?;
+        let cleaned = cleanup_message(&edited);
+        if cleaned.is_empty() {
+            anyhow::bail!("Aborting commit due to empty commit message");
+        }
+        Ok(cleaned)
+    }
+
+    /// Run pre-push for the given refspecs
+    pub(crate) fn run_pre_push_hook(&self, remote_name: &str, refspecs: &[&str]) -> Result<()> {
+        if self.hooks.no_verify || self.find_hook("pre-push").is_none() {
+            return Ok(());
+        }
+
+        let url = self.repo.find_remote(remote_name)
+            .ok()
+            .and_then(|r| r.url().map(|u| u.to_string()))
+            .unwrap_or_else(|| remote_name.to_string());
+
+        // "<local ref> <local sha> <remote ref> <remote sha>" per line; the remote side
+        // is approximated with the remote-tracking ref since libgit2 does not expose it
+        let zero = git2::Oid::zero().to_string();
+        let mut input = String::new();
+        for refspec in refspecs {
+            let (local, remote) = refspec.trim_start_matches('+')
+                .split_once(':')
+                .unwrap_or((refspec, refspec));
+            let local_sha = self.repo.refname_to_id(local)
+                .map(|oid| oid.to_string())
+                .unwrap_or_else(|_| zero.clone());
+            let tracking = remote
+                .strip_prefix("refs/heads/")
+                .map(|branch| format!("refs/remotes/{}/{}", remote_name, branch))
+                .unwrap_or_else(|| remote.to_string());
+            let remote_sha = self.repo.refname_to_id(&tracking)
+                .map(|oid| oid.to_string())
+                .unwrap_or_else(|_| zero.clone());
+            input.push_str(&format!("{} {} {} {}\n", local, local_sha, remote, remote_sha));
+        }
+
+        self.run_hook("pre-push", &[remote_name, url.as_str()], Some(input.as_bytes()))?;
+        Ok(())
+    }
+}
+
+/// Strip trailing whitespace and surrounding blank lines, like `--cleanup=whitespace`
+fn cleanup_message(message: &str) -> String {
+    let lines: Vec<&str> = message.lines().map(|line| line.trim_end()).collect();
+    let mut result = String::new();
+    let mut blank_run = false;
+
+    for line in lines {
+        if line.is_empty() {
+            blank_run = !result.is_empty();
+            continue;
+        }
+        if blank_run {
+            result.push('\n');
+            blank_run = false;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result
+}
@@ -0,0 +1,337 @@
+//! Partial staging operations
+//!
+//! This module handles hunk- and line-level stage, unstage and discard by
+//! building a partial patch from the file diff and applying it to the index
+//! or the working directory.
+
+use git2::{ApplyLocation, Delta, Diff, Patch};
+use anyhow::{Context, Result};
+
+use super::diff::diff_options;
+use super::repository::GitRepository;
+use super::types::{DiffSettings, LineSelection};
+
+/// Which part of a file diff should be applied
+enum Selection<'a> {
+    Hunk(usize),
+    Lines(&'a [LineSelection]),
+}
+
+/// Target of a partial apply
+#[derive(Clone, Copy, PartialEq)]
+enum PartialMode {
+    /// Apply index→workdir changes to the index
+    Stage,
+    /// Revert HEAD→index changes in the index
+    Unstage,
+    /// Revert index→workdir changes in the working directory
+    Discard,
+}
+
+impl GitRepository {
+    /// Stage one hunk of a file (index as returned by `get_file_diff` with `staged = false`
+    /// and the same `settings`)
+    pub fn stage_hunk(&self, file_path: &str, hunk_index: usize, settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Stage, Selection::Hunk(hunk_index), settings)
+    }
+
+    /// Unstage one hunk of a file (index as returned by `get_file_diff` with `staged = true`
+    /// and the same `settings`)
+    pub fn unstage_hunk(&self, file_path: &str, hunk_index: usize, settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Unstage, Selection::Hunk(hunk_index), settings)
+    }
+
+    /// Discard one hunk of a file from the working directory
+    pub fn discard_hunk(&self, file_path: &str, hunk_index: usize, settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Discard, Selection::Hunk(hunk_index), settings)
+    }
+
+    /// Stage the selected lines of a file
+    pub fn stage_lines(&self, file_path: &str, lines: &[LineSelection], settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Stage, Selection::Lines(lines), settings)
+    }
+
+    /// Unstage the selected lines of a file
+    pub fn unstage_lines(&self, file_path: &str, lines: &[LineSelection], settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Unstage, Selection::Lines(lines), settings)
+    }
+
+    /// Discard the selected lines of a file from the working directory
+    pub fn discard_lines(&self, file_path: &str, lines: &[LineSelection], settings: &DiffSettings) -> Result<()> {
+        self.apply_partial(file_path, PartialMode::Discard, Selection::Lines(lines), settings)
+    }
+
+    fn apply_partial(&self, file_path: &str, mode: PartialMode, selection: Selection, settings: &DiffSettings) -> Result<()> {
+        // Hunk and line numbers come from the viewer's diff, so build it the same way
+        let mut diff_opts = diff_options(settings)?;
+        diff_opts
+            .pathspec(file_path)
+            .disable_pathspec_match(true);
+
+        let diff = match mode {
+            PartialMode::Stage | PartialMode::Discard => {
+                diff_opts
+                    .include_untracked(true)
+                    .recurse_untracked_dirs(true)
+                    .show_untracked_content(true);
+                self.repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
+            }
+            PartialMode::Unstage => {
+                // No HEAD (new repository) means everything in the index is an addition
+                let head_tree = match self.repo.head() {
+                    Ok(head) => Some(head.peel_to_tree()?),
+                    Err(_) => None,
+                };
+                self.repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_opts))?
+            }
+        };
+
+        let reverse = mode != PartialMode::Stage;
+        let patch_text = build_partial_patch(&diff, file_path, &selection, reverse)?;
+        let partial = Diff::from_buffer(&patch_text)
+            .context("Failed to parse partial patch")?;
+
+        let location = match mode {
+            PartialMode::Discard => ApplyLocation::WorkDir,
+            _ => ApplyLocation::Index,
+        };
+        self.repo.apply(&partial, location, None)
+            .context(format!("Failed to apply partial changes to {}", file_path))?;
+
+        Ok(())
+    }
+}
+
+/// Build a patch containing only the selected changes of a single-file diff
+///
+/// Unselected removals are kept as context and unselected additions are dropped.
+/// When `reverse` is set the patch undoes the selected changes instead.
+fn build_partial_patch(diff: &Diff, file_path: &str, selection: &Selection, reverse: bool) -> Result<Vec<u8>> {
+    let patch = Patch::from_diff(diff, 0)?
+        .ok_or_else(|| anyhow::anyhow!("No changes found for file: {}", file_path))?;
+    let delta = patch.delta();
+
+    if delta.flags().is_binary() {
+        anyhow::bail!("Cannot partially stage a binary file: {}", file_path);
+    }
+
+    if let Selection::Hunk(index) = selection {
+        if *index >= patch.num_hunks() {
+            anyhow::bail!("Hunk {} does not exist in {}", index, file_path);
+        }
+    }
+
+    // In reverse mode additions of the original diff become removals and vice versa
+    let (add, del) = if reverse { (b'-', b'+') } else { (b'+', b'-') };
+
+    let mut body = Vec::new();
+    let mut offset: i64 = 0;
+    let mut selected_any = false;
+    let mut all_selected = true;
+
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let mut hunk_body = Vec::new();
+        let mut old_count: u32 = 0;
+        let mut new_count: u32 = 0;
+        let mut changed = false;
+
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            let origin = match line.origin() {
+                ' ' => b' ',
+                '+' => add,
+                '-' => del,
+                // End-of-file newline markers are re-emitted from the line content below
+                _ => continue,
+            };
+
+            let picked = origin != b' ' && match selection {
+                Selection::Hunk(index) => *index == hunk_idx,
+                Selection::Lines(lines) => lines.iter().any(|sel| {
+                    sel.old_lineno == line.old_lineno() && sel.new_lineno == line.new_lineno()
+                }),
+            };
+
+            let emitted = if origin == b' ' {
+                b' '
+            } else if picked {
+                changed = true;
+                origin
+            } else {
+                all_selected = false;
+                if origin == b'-' {
+                    // An unselected removal stays in the file
+                    b' '
+                } else {
+                    // An unselected addition never makes it into the file
+                    continue;
+                }
+            };
+
+            match emitted {
+                b' ' => {
+                    old_count += 1;
+                    new_count += 1;
+                }
+                b'-' => old_count += 1,
+                _ => new_count += 1,
+            }
+
+            hunk_body.push(emitted);
+            hunk_body.extend_from_slice(line.content());
+            if !line.content().ends_with(b"\n") {
+                hunk_body.extend_from_slice(b"\n\\ No newline at end of file\n");
+            }
+        }
+
+        if !changed {
+            continue;
+        }
+        selected_any = true;
+
+        let old_start = if reverse { hunk.new_start() } else { hunk.old_start() } as i64;
+        let mut new_start = old_start + offset;
+        if old_count == 0 {
+            new_start += 1;
+        }
+        if new_count == 0 {
+            new_start -= 1;
+        }
+        offset += new_count as i64 - old_count as i64;
+
+        body.extend_from_slice(
+            format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start.max(0), new_count).as_bytes()
+        );
+        body.extend_from_slice(&hunk_body);
+    }
+
+    if !selected_any {
+        anyhow::bail!("No changes selected in {}", file_path);
+    }
+
+    let added = matches!(delta.status(), Delta::Added | Delta::Untracked);
+    let deleted = delta.status() == Delta::Deleted;
+    let (old_exists, new_exists) = if reverse {
+        (!deleted, !(added && all_selected))
+    } else {
+        (!added, !(deleted && all_selected))
+    };
+
+    let mode = [delta.new_file().mode(), delta.old_file().mode()]
+        .into_iter()
+        .map(i32::from)
+        .find(|mode| *mode != 0)
+        .unwrap_or(0o100644);
+
+    let mut text = format!("diff --git a/{0} b/{0}\n", file_path);
+    if !old_exists {
+        text.push_str(&format!("new file mode {:o}\n", mode));
+    } else if !new_exists {
+        text.push_str(&format!("deleted file mode {:o}\n", mode));
+    }
+    if old_exists {
+        text.push_str(&format!("--- a/{}\n", file_path));
+    } else {
+        text.push_str("--- /dev/null\n");
+    }
+    if new_exists {
+        text.push_str(&format!("+++ b/{}\n", file_path));
+    } else {
+        text.push_str("+++ /dev/null\n");
+    }
+
+    let mut result = text.into_bytes();
+    result.extend_from_slice(&body);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+    use std::fs;
+
+    /// Repository with `content` committed as `f.txt` and `changed` in the working directory
+    fn repo_with_change(content: &str, changed: &str) -> (tempfile::TempDir, GitRepository) {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "f.txt", content, "initial");
+        fs::write(dir.path().join("f.txt"), changed).unwrap();
+        (dir, repo)
+    }
+
+    fn staged(repo: &GitRepository) -> String {
+        let index = repo.repo.index().unwrap();
+        let entry = index.get_path(std::path::Path::new("f.txt"), 0).unwrap();
+        let blob = repo.repo.find_blob(entry.id).unwrap();
+        String::from_utf8(blob.content().to_vec()).unwrap()
+    }
+
+    fn select(old_lineno: Option<u32>, new_lineno: Option<u32>) -> LineSelection {
+        LineSelection { old_lineno, new_lineno }
+    }
+
+    #[test]
+    fn test_stage_first_added_line() {
+        let (_dir, repo) = repo_with_change("a\nb\nc\n", "x\ny\na\nb\nc\n");
+        repo.stage_lines("f.txt", &[select(None, Some(1))], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "x\na\nb\nc\n");
+    }
+
+    #[test]
+    fn test_stage_last_added_line() {
+        let (_dir, repo) = repo_with_change("a\nb\nc\n", "a\nb\nc\nx\ny\n");
+        repo.stage_lines("f.txt", &[select(None, Some(5))], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "a\nb\nc\ny\n");
+    }
+
+    #[test]
+    fn test_stage_deletion_only() {
+        let (_dir, repo) = repo_with_change("a\nb\nc\nd\n", "a\nx\nd\n");
+        // Remove "b" without staging the replacement "x" or removing "c"
+        repo.stage_lines("f.txt", &[select(Some(2), None)], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "a\nc\nd\n");
+    }
+
+    #[test]
+    fn test_stage_line_without_newline_at_eof() {
+        let (_dir, repo) = repo_with_change("a\nb", "a\nb\nc");
+        // Staging the rewritten last line also brings in its missing newline
+        repo.stage_lines("f.txt", &[select(Some(2), None), select(None, Some(2))], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "a\nb\n");
+    }
+
+    #[test]
+    fn test_unstage_lines_reverses_selection() {
+        let (_dir, repo) = repo_with_change("a\nb\nc\n", "x\nb\ny\n");
+        repo.stage_file("f.txt").unwrap();
+        // Unstaging only the addition keeps the staged removal of "c"
+        repo.unstage_lines("f.txt", &[select(None, Some(3))], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "x\nb\n");
+        repo.unstage_lines("f.txt", &[select(Some(3), None)], &DiffSettings::default()).unwrap();
+        assert_eq!(staged(&repo), "x\nb\nc\n");
+    }
+
+    #[test]
+    fn test_discard_first_removed_line() {
+        let (dir, repo) = repo_with_change("a\nb\nc\n", "c\n");
+        // Restore "a" in the working directory and keep "b" deleted
+        repo.discard_lines("f.txt", &[select(Some(1), None)], &DiffSettings::default()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("f.txt")).unwrap(), "a\nc\n");
+    }
+
+    #[test]
+    fn test_stage_hunk_uses_diff_settings() {
+        let (_dir, repo) = repo_with_change("a\nb\nc\nd\ne\nf\ng\n", "A\nb\nc\nd\ne\nf\nG\n");
+        // Without context the two changes are separate hunks
+        let settings = DiffSettings { context_lines: Some(0), ..Default::default() };
+        repo.stage_hunk("f.txt", 1, &settings).unwrap();
+        assert_eq!(staged(&repo), "a\nb\nc\nd\ne\nf\nG\n");
+    }
+
+    #[test]
+    fn test_stage_hunk_out_of_range() {
+        let (_dir, repo) = repo_with_change("a\n", "b\n");
+        assert!(repo.stage_hunk("f.txt", 1, &DiffSettings::default()).is_err());
+    }
+}
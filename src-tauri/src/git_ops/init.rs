@@ -0,0 +1,408 @@
+//! Repository initialization
+//!
+//! This module creates repositories with a chosen initial branch, bare layout
+//! or template directory, and can seed the first commit with a generated
+//! `.gitignore`, README and LICENSE.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Utc};
+use git2::{Reference, Repository, RepositoryInitOptions};
+
+use super::hooks::HookOptions;
+use super::repository::GitRepository;
+use super::types::InitOptions;
+
+impl GitRepository {
+    /// Initialize a new repository
+    ///
+    /// Without `default_branch` libgit2 falls back to `init.defaultBranch` from the
+    /// git configuration, and to `master` when that is unset.
+    pub fn init(path: &str, options: &InitOptions) -> Result<Self> {
+        if let Some(branch) = &options.default_branch {
+            if !Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+                anyhow::bail!("Invalid branch name: {}", branch);
+            }
+        }
+        let starter_files = options.readme
+            || options.license.is_some()
+            || options.gitignore.as_deref().is_some_and(|kind| kind != "none");
+        if options.bare && (options.initial_commit || starter_files) {
+            anyhow::bail!("A bare repository has no working directory for the initial files");
+        }
+        if let Some(kind) = options.gitignore.as_deref().filter(|kind| *kind != "none") {
+            if gitignore_template(kind).is_none() {
+                anyhow::bail!("No .gitignore template for project type: {}", kind);
+            }
+        }
+        if let Some(license) = &options.license {
+            license_text(license, "")?;
+        }
+
+        let mut init_opts = RepositoryInitOptions::new();
+        init_opts.bare(options.bare);
+        if let Some(branch) = &options.default_branch {
+            init_opts.initial_head(branch);
+        }
+        if let Some(template) = &options.template_path {
+            if !Path::new(template).is_dir() {
+                anyhow::bail!("Template directory not found: {}", template);
+            }
+            init_opts.template_path(Path::new(template));
+        }
+
+        let repo = Repository::init_opts(path, &init_opts)
+            .context(format!("Failed to initialize repository at {}", path))?;
+        let initialized = Self { repo, hooks: HookOptions::default() };
+
+        if options.initial_commit || starter_files {
+            let files = initialized.write_starter_files(options)?;
+            if options.initial_commit {
+                initialized.commit_starter_files(&files)?;
+            }
+        }
+        Ok(initialized)
+    }
+
+    /// Write the requested starter files and return their names
+    ///
+    /// Files that already exist are kept as they are rather than overwritten.
+    fn write_starter_files(&self, options: &InitOptions) -> Result<Vec<&'static str>> {
+        let workdir = self.repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+
+        let mut files = Vec::new();
+        if let Some(kind) = options.gitignore.as_deref().filter(|kind| *kind != "none") {
+            let template = gitignore_template(kind)
+                .ok_or_else(|| anyhow::anyhow!("No .gitignore template for project type: {}", kind))?;
+            files.push((".gitignore", format!("{}\n{}", template.trim_start(), GITIGNORE_COMMON)));
+        }
+        if options.readme {
+            let name = options.name.clone()
+                .filter(|name| !name.trim().is_empty())
+                .or_else(|| workdir.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            let mut readme = format!("# {}\n", name.trim());
+            if let Some(description) = options.description.as_deref().filter(|d| !d.trim().is_empty()) {
+                readme.push_str(&format!("\n{}\n", description.trim()));
+            }
+            files.push(("README.md", readme));
+        }
+        if let Some(license) = &options.license {
+            let signature = self.repo.signature().ok();
+            let author = signature.as_ref().and_then(|s| s.name()).unwrap_or("The authors");
+            files.push(("LICENSE", license_text(license, author)?));
+        }
+
+        for (name, content) in &files {
+            let full_path = workdir.join(name);
+            if !full_path.exists() {
+                fs::write(&full_path, content)
+                    .context(format!("Failed to write {}", name))?;
+            }
+        }
+        Ok(files.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Commit the starter files as the root commit, signed when signing is configured
+    fn commit_starter_files(&self, files: &[&str]) -> Result<()> {
+        let signature = self.repo.signature()
+            .context("Cannot create the initial commit: please set user.name and user.email first")?;
+
+        let mut index = self.repo.index()?;
+        for name in files {
+            index.add_path(Path::new(name))?;
+        }
+        index.write()?;
+
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        self.create_commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[])?;
+        Ok(())
+    }
+}
+
+/// `.gitignore` entries for a project type reported by `detect_project_type`
+fn gitignore_template(kind: &str) -> Option<&'static str> {
+    let template = match kind {
+        "node" => r#"
+# Dependencies
+node_modules/
+.pnp
+.pnp.js
+
+# Build output
+dist/
+build/
+coverage/
+
+# Logs
+npm-debug.log*
+yarn-debug.log*
+yarn-error.log*
+pnpm-debug.log*
+
+# Environment
+.env
+.env.local
+"#,
+        "python" => r#"
+# Byte-compiled files
+__pycache__/
+*.py[cod]
+
+# Packaging
+build/
+dist/
+*.egg-info/
+
+# Virtual environments
+.venv/
+venv/
+env/
+
+# Tests and tooling
+.pytest_cache/
+.mypy_cache/
+.coverage
+htmlcov/
+.env
+"#,
+        "java" => r#"
+# Compiled classes and archives
+*.class
+*.jar
+*.war
+*.ear
+
+# Build output
+target/
+build/
+out/
+.gradle/
+
+# IDE
+.idea/
+*.iml
+"#,
+        "go" => r#"
+# Binaries
+*.exe
+*.dll
+*.so
+*.dylib
+/bin/
+
+# Tests
+*.test
+*.out
+coverage.txt
+
+# Workspace
+go.work
+"#,
+        "rust" => r#"
+# Build output
+/target/
+
+# Backup files from rustfmt
+**/*.rs.bk
+"#,
+        "cpp" => r#"
+# Objects and libraries
+*.o
+*.obj
+*.a
+*.lib
+*.so
+*.dylib
+*.dll
+*.exe
+
+# Build output
+build/
+cmake-build-*/
+CMakeFiles/
+CMakeCache.txt
+"#,
+        "csharp" => r#"
+# Build output
+bin/
+obj/
+
+# Visual Studio
+.vs/
+*.user
+*.suo
+
+# Packages
+*.nupkg
+packages/
+"#,
+        "ruby" => r#"
+# Bundler
+/.bundle/
+/vendor/bundle/
+
+# Build and test output
+/pkg/
+/coverage/
+/tmp/
+*.gem
+"#,
+        "php" => r#"
+# Composer
+/vendor/
+
+# Environment
+.env
+
+# Tests
+.phpunit.result.cache
+"#,
+        "swift" => r#"
+# Swift Package Manager
+.build/
+.swiftpm/
+
+# Xcode
+xcuserdata/
+DerivedData/
+*.xcuserstate
+"#,
+        "kotlin" => r#"
+# Compiled classes and archives
+*.class
+*.jar
+
+# Gradle
+.gradle/
+build/
+out/
+
+# IDE
+.idea/
+*.iml
+local.properties
+"#,
+        _ => return None,
+    };
+    Some(template)
+}
+
+/// Entries added to every generated `.gitignore`
+const GITIGNORE_COMMON: &str = "# OS files
+.DS_Store
+Thumbs.db
+";
+
+/// License text with the current year and `author` filled in
+fn license_text(license: &str, author: &str) -> Result<String> {
+    let template = match license {
+        "MIT" => MIT_LICENSE,
+        "ISC" => ISC_LICENSE,
+        "BSD-3-Clause" => BSD_3_CLAUSE_LICENSE,
+        "Unlicense" => UNLICENSE,
+        _ => anyhow::bail!("Unsupported license: {}. Use MIT, ISC, BSD-3-Clause or Unlicense", license),
+    };
+    Ok(template
+        .replace("{year}", &Utc::now().year().to_string())
+        .replace("{author}", author))
+}
+
+const MIT_LICENSE: &str = r#"MIT License
+
+Copyright (c) {year} {author}
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+"#;
+
+const ISC_LICENSE: &str = r#"ISC License
+
+Copyright (c) {year} {author}
+
+Permission to use, copy, modify, and/or distribute this software for any
+purpose with or without fee is hereby granted, provided that the above
+copyright notice and this permission notice appear in all copies.
+
+THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+"#;
+
+const BSD_3_CLAUSE_LICENSE: &str = r#"BSD 3-Clause License
+
+Copyright (c) {year}, {author}
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+3. Neither the name of the copyright holder nor the names of its
+   contributors may be used to endorse or promote products derived from
+   this software without specific prior written permission.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+"#;
+
+const UNLICENSE: &str = r#"This is free and unencumbered software released into the public domain.
+
+Anyone is free to copy, modify, publish, use, compile, sell, or
+distribute this software, either in source code form or as a compiled
+binary, for any purpose, commercial or non-commercial, and by any
+means.
+
+In jurisdictions that recognize copyright laws, the author or authors
+of this software dedicate any and all copyright interest in the
+software to the public domain. We make this dedication for the benefit
+of the public at large and to the detriment of our heirs and
+successors. We intend this dedication to be an overt act of
+relinquishment in perpetuity of all present and future rights to this
+software under copyright law.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF
+MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY CLAIM, DAMAGES OR
+OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
+ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR
+OTHER DEALINGS IN THE SOFTWARE.
+
+For more information, please refer to <https://unlicense.org>
+"#;
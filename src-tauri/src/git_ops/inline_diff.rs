@@ -0,0 +1,251 @@
+//! Intra-line diff highlighting
+//!
+//! This module pairs removed and added lines inside a hunk and computes the
+//! word- or character-level ranges that changed between them, so the frontend
+//! only has to paint the spans attached to each `DiffLine`.
+
+use super::types::{DiffHunk, DiffLine, DiffSpan};
+
+/// Above this many LCS cells the middle of a line pair is marked changed as a whole
+const MAX_LCS_CELLS: usize = 250_000;
+
+/// Granularity of intra-line changes
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum InlineMode {
+    Word,
+    Char,
+}
+
+impl InlineMode {
+    /// Parse the `inline_diff` setting; `None` turns highlighting off
+    pub(crate) fn from_setting(value: Option<&str>) -> anyhow::Result<Option<Self>> {
+        match value {
+            None | Some("") | Some("word") => Ok(Some(InlineMode::Word)),
+            Some("char") => Ok(Some(InlineMode::Char)),
+            Some("none") => Ok(None),
+            Some(other) => anyhow::bail!("Unknown inline diff mode: {}", other),
+        }
+    }
+}
+
+/// A token of a line with its UTF-16 range
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Fill `spans` of every removed/added line pair in a hunk
+///
+/// A run of `-` lines directly followed by a run of `+` lines is paired line by line,
+/// like git's diff-highlight; surplus lines of the longer run stay unpaired.
+pub(crate) fn highlight_hunk(hunk: &mut DiffHunk, mode: InlineMode) {
+    let lines = &mut hunk.lines;
+    let mut i = 0;
+
+    while i < lines.len() {
+        if lines[i].origin != '-' {
+            i += 1;
+            continue;
+        }
+
+        let del_start = i;
+        while i < lines.len() && lines[i].origin == '-' {
+            i += 1;
+        }
+        let add_start = i;
+        while i < lines.len() && lines[i].origin == '+' {
+            i += 1;
+        }
+
+        let pairs = (add_start - del_start).min(i - add_start);
+        for k in 0..pairs {
+            let (removed, added) = lines.split_at_mut(add_start);
+            highlight_pair(&mut removed[del_start + k], &mut added[k], mode);
+        }
+    }
+}
+
+/// Compute the changed spans of one removed/added line pair
+fn highlight_pair(old: &mut DiffLine, new: &mut DiffLine, mode: InlineMode) {
+    let old_tokens = tokenize(old.content.trim_end_matches(['\r', '\n']), mode);
+    let new_tokens = tokenize(new.content.trim_end_matches(['\r', '\n']), mode);
+
+    // Common prefix and suffix are cheap to strip and cover most single edits
+    let prefix = old_tokens.iter()
+        .zip(&new_tokens)
+        .take_while(|(a, b)| a.text == b.text)
+        .count();
+    let suffix = old_tokens[prefix..].iter().rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(a, b)| a.text == b.text)
+        .count();
+
+    let old_mid = &old_tokens[prefix..old_tokens.len() - suffix];
+    let new_mid = &new_tokens[prefix..new_tokens.len() - suffix];
+    let (old_kept, new_kept) = if old_mid.len() * new_mid.len() <= MAX_LCS_CELLS {
+        lcs_matches(old_mid, new_mid)
+    } else {
+        (vec![false; old_mid.len()], vec![false; new_mid.len()])
+    };
+
+    // Lines sharing nothing at all are simply replaced; highlighting them is noise
+    let shared = prefix + suffix + old_kept.iter().filter(|k| **k).count();
+    if shared == 0 {
+        return;
+    }
+
+    old.spans = changed_spans(old_mid, &old_kept);
+    new.spans = changed_spans(new_mid, &new_kept);
+}
+
+/// Split a line into words, whitespace runs and single punctuation characters (or characters)
+fn tokenize(line: &str, mode: InlineMode) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut utf16_pos = 0;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((byte_start, c)) = chars.next() {
+        let mut byte_end = byte_start + c.len_utf8();
+        let mut width = c.len_utf16();
+
+        if mode == InlineMode::Word {
+            let class = char_class(c);
+            if class != CharClass::Punct {
+                while let Some(&(idx, next)) = chars.peek() {
+                    if char_class(next) != class {
+                        break;
+                    }
+                    byte_end = idx + next.len_utf8();
+                    width += next.len_utf16();
+                    chars.next();
+                }
+            }
+        }
+
+        tokens.push(Token {
+            text: &line[byte_start..byte_end],
+            start: utf16_pos,
+            end: utf16_pos + width,
+        });
+        utf16_pos += width;
+    }
+
+    tokens
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Word,
+    Space,
+    Punct,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else if c.is_whitespace() {
+        CharClass::Space
+    } else {
+        CharClass::Punct
+    }
+}
+
+/// Mark the tokens of each side that belong to a longest common subsequence
+fn lcs_matches(a: &[Token], b: &[Token]) -> (Vec<bool>, Vec<bool>) {
+    let (n, m) = (a.len(), b.len());
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    let idx = |i: usize, j: usize| i * (m + 1) + j;
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[idx(i, j)] = if a[i].text == b[j].text {
+                table[idx(i + 1, j + 1)] + 1
+            } else {
+                table[idx(i + 1, j)].max(table[idx(i, j + 1)])
+            };
+        }
+    }
+
+    let mut a_kept = vec![false; n];
+    let mut b_kept = vec![false; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i].text == b[j].text {
+            a_kept[i] = true;
+            b_kept[j] = true;
+            i += 1;
+            j += 1;
+        } else if table[idx(i + 1, j)] >= table[idx(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    (a_kept, b_kept)
+}
+
+/// Merge adjacent unmatched tokens into spans
+fn changed_spans(tokens: &[Token], kept: &[bool]) -> Vec<DiffSpan> {
+    let mut spans: Vec<DiffSpan> = Vec::new();
+
+    for (token, kept) in tokens.iter().zip(kept) {
+        if *kept {
+            continue;
+        }
+        match spans.last_mut() {
+            Some(last) if last.end == token.start => last.end = token.end,
+            _ => spans.push(DiffSpan { start: token.start, end: token.end }),
+        }
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(origin: char, content: &str) -> DiffLine {
+        DiffLine {
+            origin,
+            content: content.to_string(),
+            old_lineno: None,
+            new_lineno: None,
+            spans: Vec::new(),
+        }
+    }
+
+    fn ranges(line: &DiffLine) -> Vec<(usize, usize)> {
+        line.spans.iter().map(|s| (s.start, s.end)).collect()
+    }
+
+    #[test]
+    fn pairs_removed_and_added_lines() {
+        let mut hunk = DiffHunk {
+            old_start: 1,
+            old_lines: 2,
+            new_start: 1,
+            new_lines: 2,
+            header: String::new(),
+            lines: vec![
+                line('-', "let näme = \"old\";\n"),
+                line('-', "unpaired\n"),
+                line('+', "let näme = \"new\";\n"),
+                line(' ', "context\n"),
+            ],
+        };
+
+        highlight_hunk(&mut hunk, InlineMode::Word);
+        assert_eq!(ranges(&hunk.lines[0]), vec![(12, 15)]);
+        assert_eq!(ranges(&hunk.lines[2]), vec![(12, 15)]);
+        assert!(hunk.lines[1].spans.is_empty());
+
+        hunk.lines = vec![line('-', "value=1234\n"), line('+', "value=1284\n")];
+        highlight_hunk(&mut hunk, InlineMode::Word);
+        assert_eq!(ranges(&hunk.lines[1]), vec![(6, 10)]);
+        highlight_hunk(&mut hunk, InlineMode::Char);
+        assert_eq!(ranges(&hunk.lines[1]), vec![(8, 9)]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Commit inspection
+//!
+//! This module builds the detailed view of a single commit: author and
+//! committer identities, the message split into subject/body/trailers,
+//! signature status and the changed files with line statistics.
+
+use git2::{Delta, DiffFindOptions, Patch, Time};
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
+
+use super::repository::GitRepository;
+use super::types::{
+    CommitDetails, CommitFileChange, CommitIdentity, CommitTrailer, SignatureVerification,
+};
+
+impl GitRepository {
+    /// Get the full details of a commit
+    ///
+    /// Changed files are relative to the first parent, so merges list what they
+    /// brought into the current branch.
+    pub fn get_commit_details(&self, commit_hash: &str) -> Result<CommitDetails> {
+        let commit = self.repo.revparse_single(commit_hash)
+            .context(format!("Failed to find commit: {}", commit_hash))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", commit_hash))?;
+
+        let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+        let trailers: Vec<CommitTrailer> = git2::message_trailers_strs(&message)
+            .map(|trailers| {
+                trailers.iter()
+                    .map(|(key, value)| CommitTrailer {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (subject, body) = split_message(&message, !trailers.is_empty());
+
+        let signature = self.verify_commit_signature(&commit.id().to_string())
+            .unwrap_or_else(|e| SignatureVerification {
+                status: "error".to_string(),
+                format: None,
+                signer: None,
+                key: None,
+                output: Some(e.to_string()),
+            });
+
+        let files = self.commit_file_changes(&commit)?;
+        let insertions = files.iter().map(|f| f.insertions).sum();
+        let deletions = files.iter().map(|f| f.deletions).sum();
+        let author = identity(&commit.author());
+        let committer = identity(&commit.committer());
+
+        Ok(CommitDetails {
+            hash: commit.id().to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            author,
+            committer,
+            subject,
+            body,
+            trailers,
+            message,
+            signature,
+            files,
+            insertions,
+            deletions,
+        })
+    }
+
+    /// Files changed by a commit compared to its first parent, with renames and copies detected
+    pub(crate) fn commit_file_changes(&self, commit: &git2::Commit) -> Result<Vec<CommitFileChange>> {
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let mut diff = self.repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))?;
+
+        let mut files = Vec::new();
+        for (idx, delta) in diff.deltas().enumerate() {
+            let status = match delta.status() {
+                Delta::Added => "added",
+                Delta::Deleted => "deleted",
+                Delta::Renamed => "renamed",
+                Delta::Copied => "copied",
+                Delta::Typechange => "typechange",
+                _ => "modified",
+            };
+
+            let path_of = |file: git2::DiffFile| {
+                file.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()
+            };
+            let path = if delta.status() == Delta::Deleted {
+                path_of(delta.old_file())
+            } else {
+                path_of(delta.new_file())
+            };
+            let old_path = matches!(delta.status(), Delta::Renamed | Delta::Copied)
+                .then(|| path_of(delta.old_file()));
+
+            let (insertions, deletions, binary) = match Patch::from_diff(&diff, idx)? {
+                Some(patch) => {
+                    let (_, additions, removals) = patch.line_stats()?;
+                    (additions, removals, patch.delta().flags().is_binary())
+                }
+                None => (0, 0, delta.flags().is_binary()),
+            };
+
+            files.push(CommitFileChange {
+                path,
+                old_path,
+                status: status.to_string(),
+                insertions,
+                deletions,
+                binary,
+            });
+        }
+
+        Ok(files)
+    }
+}
+
+/// Build a `CommitIdentity` from a git signature, keeping its own time zone
+fn identity(signature: &git2::Signature) -> CommitIdentity {
+    let time = signature.when();
+    CommitIdentity {
+        name: String::from_utf8_lossy(signature.name_bytes()).to_string(),
+        email: String::from_utf8_lossy(signature.email_bytes()).to_string(),
+        date: format_time(&time),
+        timestamp: time.seconds(),
+        timezone: format!(
+            "{}{:02}{:02}",
+            time.sign(),
+            time.offset_minutes().abs() / 60,
+            time.offset_minutes().abs() % 60
+        ),
+    }
+}
+
+/// Format a git time as RFC 3339 in its recorded offset
+fn format_time(time: &Time) -> String {
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|offset| {
+            DateTime::from_timestamp(time.seconds(), 0).map(|dt| dt.with_timezone(&offset).to_rfc3339())
+        })
+        .unwrap_or_else(|| format!("Invalid timestamp: {}", time.seconds()))
+}
+
+/// Split a commit message into subject and body
+///
+/// The subject is the first paragraph joined into one line, as `git log --format=%s`
+/// shows it. When `has_trailers` is set the trailing trailer block is left out of the body.
+fn split_message(message: &str, has_trailers: bool) -> (String, String) {
+    let message = message.trim();
+    let (subject, rest) = match message.split_once("\n\n") {
+        Some((subject, rest)) => (subject, rest.trim()),
+        None => (message, ""),
+    };
+    let subject = subject.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+
+    let mut body = rest;
+    if has_trailers {
+        let (head, last) = match rest.rsplit_once("\n\n") {
+            Some((head, last)) => (head, last),
+            None => ("", rest),
+        };
+        let is_trailer_block = last.lines().all(|line| {
+            line.starts_with(char::is_whitespace)
+                || line.split_once(':').is_some_and(|(key, _)| {
+                    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '-')
+                })
+        });
+        if is_trailer_block {
+            body = head.trim_end();
+        }
+    }
+
+    (subject, body.to_string())
+}
@@ -0,0 +1,283 @@
+//! Git LFS support
+//!
+//! This module recognises LFS-tracked paths through the `filter=lfs` attribute
+//! and parses pointer files natively, so diffs, blame and status can show the
+//! real objects. Transfers and locks are delegated to the `git-lfs` command.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use git2::AttrCheckFlags;
+use sha2::{Digest, Sha256};
+
+use super::repository::GitRepository;
+use super::types::{LfsFileInfo, LfsInfo, LfsLock, LfsPointer};
+
+/// First line of every pointer file
+const LFS_SPEC_LINE: &str = "version https://git-lfs.github.com/spec/v1";
+/// Pointer files are never larger than this
+pub(crate) const MAX_LFS_POINTER_BYTES: u64 = 1024;
+
+impl GitRepository {
+    /// Tracked patterns from `.gitattributes` and whether `git-lfs` is installed
+    pub fn get_lfs_info(&self) -> Result<LfsInfo> {
+        let mut tracked_patterns = Vec::new();
+        if let Some(workdir) = self.repo.workdir() {
+            if let Ok(content) = fs::read_to_string(workdir.join(".gitattributes")) {
+                for line in content.lines() {
+                    let mut fields = line.split_whitespace();
+                    let Some(pattern) = fields.next() else { continue };
+                    if !pattern.starts_with('#') && fields.any(|attr| attr == "filter=lfs") {
+                        tracked_patterns.push(pattern.to_string());
+                    }
+                }
+            }
+        }
+
+        let installed = Command::new("git")
+            .args(["lfs", "version"])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+
+        Ok(LfsInfo { tracked_patterns, installed })
+    }
+
+    /// LFS files in the index with whether the working directory holds the pointer or the content
+    pub fn get_lfs_files(&self) -> Result<Vec<LfsFileInfo>> {
+        let index = self.repo.index()?;
+        let workdir = self.repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+        let odb = self.repo.odb()?;
+        let mut files = Vec::new();
+
+        for entry in index.iter() {
+            let path = String::from_utf8_lossy(&entry.path).to_string();
+            if !self.is_lfs_path(&path) {
+                continue;
+            }
+            // The index records the smudged file's size, so check the blob itself
+            match odb.read_header(entry.id) {
+                Ok((size, _)) if size as u64 <= MAX_LFS_POINTER_BYTES => {}
+                _ => continue,
+            }
+            let Ok(blob) = self.repo.find_blob(entry.id) else { continue };
+            let Some((oid, size)) = parse_lfs_pointer(blob.content()) else { continue };
+
+            // Only files small enough to be a pointer are read; anything larger is the content
+            let full_path = workdir.join(&path);
+            let state = match fs::metadata(&full_path) {
+                Err(_) => "missing",
+                Ok(meta) if meta.len() > MAX_LFS_POINTER_BYTES => "smudged",
+                Ok(_) => match fs::read(&full_path) {
+                    Err(_) => "missing",
+                    Ok(data) if parse_lfs_pointer(&data).is_some() => "pointer",
+                    Ok(_) => "smudged",
+                },
+            };
+
+            files.push(LfsFileInfo {
+                path,
+                downloaded: self.lfs_object_path(&oid).is_file(),
+                oid,
+                size,
+                state: state.to_string(),
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Download LFS objects and replace pointer files with their content
+    ///
+    /// `include` limits the transfer to the given paths or patterns.
+    pub fn lfs_pull(&self, include: &[String]) -> Result<String> {
+        let mut args = vec!["pull".to_string()];
+        if !include.is_empty() {
+            args.push(format!("--include={}", include.join(",")));
+        }
+        self.run_git_lfs(&args.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    /// List the locks held on the LFS server
+    pub fn lfs_locks(&self) -> Result<Vec<LfsLock>> {
+        let output = self.run_git_lfs(&["locks", "--json"])?;
+        let value: serde_json::Value = serde_json::from_str(&output)
+            .context("Unexpected output from git lfs locks")?;
+        Ok(value.as_array()
+            .map(|locks| locks.iter().map(parse_lfs_lock).collect())
+            .unwrap_or_default())
+    }
+
+    /// Lock a file on the LFS server
+    pub fn lfs_lock(&self, path: &str) -> Result<LfsLock> {
+        let output = self.run_git_lfs(&["lock", "--json", path])?;
+        let value: serde_json::Value = serde_json::from_str(&output)
+            .context("Unexpected output from git lfs lock")?;
+        Ok(parse_lfs_lock(&value))
+    }
+
+    /// Release a lock; `force` also releases locks held by someone else
+    pub fn lfs_unlock(&self, path: &str, force: bool) -> Result<()> {
+        let mut args = vec!["unlock", "--json"];
+        if force {
+            args.push("--force");
+        }
+        args.push(path);
+        self.run_git_lfs(&args)?;
+        Ok(())
+    }
+
+    /// Whether `.gitattributes` routes a path through the LFS filter
+    pub(crate) fn is_lfs_path(&self, path: &str) -> bool {
+        matches!(
+            self.repo.get_attr(Path::new(path), "filter", AttrCheckFlags::FILE_THEN_INDEX),
+            Ok(Some("lfs"))
+        )
+    }
+
+    /// Describe a pointer, checking whether its object is in the local store
+    pub(crate) fn lfs_pointer(&self, oid: String, size: u64) -> LfsPointer {
+        let downloaded = self.lfs_object_path(&oid).is_file();
+        LfsPointer { oid, size, downloaded }
+    }
+
+    /// Location of an object in the local LFS store
+    pub(crate) fn lfs_object_path(&self, oid: &str) -> PathBuf {
+        let mut path = self.repo.path().join("lfs").join("objects");
+        if oid.len() > 4 {
+            path = path.join(&oid[0..2]).join(&oid[2..4]);
+        }
+        path.join(oid)
+    }
+
+    /// Whether a working directory file is the smudged content of the pointer in the index
+    ///
+    /// libgit2 does not run the LFS filter, so such files would otherwise look modified.
+    pub(crate) fn is_smudged_lfs_file(&self, path: &str) -> bool {
+        let Some(workdir) = self.repo.workdir() else { return false };
+        let Ok(index) = self.repo.index() else { return false };
+        let Some(entry) = index.get_path(Path::new(path), 0) else { return false };
+        let Ok(blob) = self.repo.find_blob(entry.id) else { return false };
+        let Some((oid, size)) = parse_lfs_pointer(blob.content()) else { return false };
+
+        let full_path = workdir.join(path);
+        if fs::metadata(&full_path).ok().map(|m| m.len()) != Some(size) {
+            return false;
+        }
+        sha256_file(&full_path).is_ok_and(|digest| digest == oid)
+    }
+
+    /// Run `git lfs` in the working directory and return its standard output
+    fn run_git_lfs(&self, args: &[&str]) -> Result<String> {
+        let workdir = self.repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+        let output = Command::new("git")
+            .arg("lfs")
+            .args(args)
+            .current_dir(workdir)
+            .output()
+            .context("Failed to run git lfs")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("'lfs' is not a git command") {
+                anyhow::bail!("git-lfs is not installed. Please install it from https://git-lfs.com");
+            }
+            anyhow::bail!("git lfs {} failed: {}", args.first().unwrap_or(&""), stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// Parse a pointer file into its sha256 oid and object size
+pub(crate) fn parse_lfs_pointer(data: &[u8]) -> Option<(String, u64)> {
+    if data.len() as u64 > MAX_LFS_POINTER_BYTES {
+        return None;
+    }
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+    if lines.next()?.trim_end() != LFS_SPEC_LINE {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("oid sha256:") {
+            let value = value.trim_end();
+            if value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+                oid = Some(value.to_ascii_lowercase());
+            }
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.trim_end().parse().ok();
+        }
+    }
+    Some((oid?, size?))
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn parse_lfs_lock(value: &serde_json::Value) -> LfsLock {
+    let text = |v: &serde_json::Value| v.as_str().unwrap_or("").to_string();
+    LfsLock {
+        id: text(&value["id"]),
+        path: text(&value["path"]),
+        owner: text(&value["owner"]["name"]),
+        locked_at: text(&value["locked_at"]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    fn pointer(oid: &str, size: &str) -> String {
+        format!("{}\noid sha256:{}\nsize {}\n", LFS_SPEC_LINE, oid, size)
+    }
+
+    #[test]
+    fn test_parse_lfs_pointer() {
+        assert_eq!(parse_lfs_pointer(pointer(OID, "12345").as_bytes()), Some((OID.to_string(), 12345)));
+        // Upper-case oids are normalised and CRLF line endings accepted
+        let crlf = pointer(&OID.to_uppercase(), "7").replace('\n', "\r\n");
+        assert_eq!(parse_lfs_pointer(crlf.as_bytes()), Some((OID.to_string(), 7)));
+    }
+
+    #[test]
+    fn test_parse_lfs_pointer_rejects_other_content() {
+        assert_eq!(parse_lfs_pointer(b"hello\n"), None);
+        assert_eq!(parse_lfs_pointer(pointer(&OID[..60], "1").as_bytes()), None);
+        assert_eq!(parse_lfs_pointer(pointer(OID, "big").as_bytes()), None);
+        assert_eq!(parse_lfs_pointer(format!("{}\nsize 1\n", LFS_SPEC_LINE).as_bytes()), None);
+        let padded = pointer(OID, "1") + &"x".repeat(MAX_LFS_POINTER_BYTES as usize);
+        assert_eq!(parse_lfs_pointer(padded.as_bytes()), None);
+    }
+
+    #[test]
+    fn test_is_lfs_path() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitattributes"), "*.psd filter=lfs diff=lfs merge=lfs -text\n").unwrap();
+        let repo = GitRepository::open(dir.path().to_str().unwrap()).unwrap();
+        assert!(repo.is_lfs_path("art/cover.psd"));
+        assert!(!repo.is_lfs_path("README.md"));
+    }
+}
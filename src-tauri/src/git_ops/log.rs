@@ -0,0 +1,457 @@
+//! Commit log queries
+//!
+//! This module provides a paginated, filterable commit log similar to
+//! `git log`: multiple start refs, path filtering (optionally following
+//! renames), author/committer/date/message filters, first-parent history
+//! and date or topological ordering.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use git2::{Commit, DiffFindOptions, Oid, Repository, Revwalk, Sort};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use super::repository::GitRepository;
+use super::types::{CommitPage, CommitQuery};
+
+/// Page size used when the query does not set one
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Walk behind a log query
+enum LogWalk<'r> {
+    Topo(Revwalk<'r>),
+    Date(DateWalk<'r>),
+}
+
+/// Newest-first walk over a queue ordered by commit time, as plain `git log` does
+///
+/// Unlike libgit2's time sorting this never looks at more of the history than
+/// it returns, and the queue left after a page is where the next page starts.
+struct DateWalk<'r> {
+    repo: &'r Repository,
+    queue: BinaryHeap<(i64, Reverse<u64>, Oid)>, // 同一时间先入队者先出，保证子提交在父提交之前
+    seen: HashSet<Oid>,
+    next_seq: u64,
+    first_parent: bool,
+}
+
+/// Compiled form of the query filters
+struct LogFilter {
+    author: Option<Regex>,
+    committer: Option<Regex>,
+    grep: Option<Regex>,
+    since: Option<i64>,
+    until: Option<i64>,
+}
+
+impl GitRepository {
+    /// Query the commit log
+    ///
+    /// Pass `next_cursor` of the previous page as `cursor` to continue, plus the
+    /// path the file had at that point in history when following renames. In date
+    /// order the cursor lists the commits still waiting to be walked, so the next
+    /// page resumes from there. Topological order needs the whole graph, so each
+    /// page walks again from the start and the cursor is the last returned commit.
+    pub fn query_commits(&self, query: &CommitQuery) -> Result<CommitPage> {
+        let limit = query.limit.filter(|l| *l > 0).unwrap_or(DEFAULT_PAGE_SIZE);
+        let filter = LogFilter::new(query)?;
+
+        let topo = match query.order.as_deref() {
+            // Newest first, streamed without walking the whole history up front
+            None | Some("date") => false,
+            // Like `--topo-order`: lines of history are not interleaved
+            Some("topo") => true,
+            Some(other) => anyhow::bail!("Unknown commit order: {}", other),
+        };
+
+        let mut path = query.path.clone()
+            .map(|p| p.trim_matches('/').to_string())
+            .filter(|p| !p.is_empty());
+        let mut position = None;
+        if let Some(cursor) = &query.cursor {
+            let (oids, cursor_path) = cursor.split_once(':').unwrap_or((cursor.as_str(), ""));
+            let oids = oids.split(',')
+                .map(Oid::from_str)
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .context("Invalid commit log cursor")?;
+            position = Some(oids);
+            if query.follow_renames && !cursor_path.is_empty() {
+                path = Some(cursor_path.to_string());
+            }
+        }
+
+        let starts = match (&position, topo) {
+            (Some(frontier), false) => frontier.clone(),
+            _ => self.log_start_commits(query)?,
+        };
+        if starts.is_empty() {
+            // Unborn branch: no history yet
+            return Ok(CommitPage { commits: Vec::new(), next_cursor: None });
+        }
+
+        let mut walk = if topo {
+            let mut revwalk = self.repo.revwalk()?;
+            revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+            if query.first_parent {
+                revwalk.simplify_first_parent()?;
+            }
+            for oid in &starts {
+                revwalk.push(*oid)?;
+            }
+            LogWalk::Topo(revwalk)
+        } else {
+            LogWalk::Date(DateWalk::new(&self.repo, &starts, query.first_parent)
+                .context("Commit log cursor is no longer part of the history; reload the log")?)
+        };
+        let mut resume_after = if topo { position.and_then(|oids| oids.first().copied()) } else { None };
+
+        let mut commits = Vec::new();
+        let mut next_cursor = None;
+
+        loop {
+            let commit = match &mut walk {
+                LogWalk::Topo(revwalk) => {
+                    let Some(oid) = revwalk.next() else { break };
+                    let oid = oid?;
+                    // Topological pages walk the earlier pages again but only look at the oids
+                    if let Some(after) = resume_after {
+                        if oid == after {
+                            resume_after = None;
+                        }
+                        continue;
+                    }
+                    self.repo.find_commit(oid)?
+                }
+                LogWalk::Date(date_walk) => match date_walk.next_commit()? {
+                    Some(commit) => commit,
+                    None => break,
+                },
+            };
+
+            if let Some(current) = path.clone() {
+                let (touched, renamed_from) = self.commit_touches_path(
+                    &commit, &current, query.first_parent, query.follow_renames,
+                )?;
+                if let Some(old_path) = renamed_from {
+                    path = Some(old_path);
+                }
+                if !touched {
+                    continue;
+                }
+            }
+
+            if !filter.matches(&commit) {
+                continue;
+            }
+
+            commits.push(self.commit_info(&commit));
+            if commits.len() >= limit {
+                let position = match &walk {
+                    LogWalk::Topo(_) => commit.id().to_string(),
+                    LogWalk::Date(date_walk) => date_walk.frontier().iter()
+                        .map(Oid::to_string)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                };
+                if !position.is_empty() {
+                    next_cursor = Some(match (&path, query.follow_renames) {
+                        (Some(p), true) => format!("{}:{}", position, p),
+                        _ => position,
+                    });
+                }
+                break;
+            }
+        }
+
+        if resume_after.is_some() {
+            anyhow::bail!("Commit log cursor is no longer part of the history; reload the log");
+        }
+
+        Ok(CommitPage { commits, next_cursor })
+    }
+
+    /// Commits the log starts from: the requested refs, every branch, or HEAD
+    fn log_start_commits(&self, query: &CommitQuery) -> Result<Vec<Oid>> {
+        let mut starts = Vec::new();
+        if query.all_branches {
+            for glob in ["refs/heads/*", "refs/remotes/*"] {
+                for reference in self.repo.references_glob(glob)? {
+                    if let Ok(commit) = reference?.peel_to_commit() {
+                        starts.push(commit.id());
+                    }
+                }
+            }
+        }
+        for spec in &query.refs {
+            let object = self.repo.revparse_single(spec)
+                .context(format!("无法找到引用: {}", spec))?;
+            let commit = object.peel_to_commit()
+                .context(format!("引用 {} 不是有效的提交", spec))?;
+            starts.push(commit.id());
+        }
+        if !query.all_branches && query.refs.is_empty() {
+            if let Ok(head) = self.repo.head().and_then(|head| head.peel_to_commit()) {
+                starts.push(head.id());
+            }
+        }
+        Ok(starts)
+    }
+
+    /// Whether a commit changes `path`, and the path's previous name if the commit renamed it
+    ///
+    /// Like git's default history simplification, a merge only counts when it differs
+    /// from every parent. Comparing tree entry ids makes this cheap for directories too.
+    fn commit_touches_path(
+        &self,
+        commit: &Commit,
+        path: &str,
+        first_parent: bool,
+        follow_renames: bool,
+    ) -> Result<(bool, Option<String>)> {
+        let entry_id = |commit: &Commit| -> Result<Option<Oid>> {
+            Ok(commit.tree()?.get_path(std::path::Path::new(path)).ok().map(|e| e.id()))
+        };
+
+        let own = entry_id(commit)?;
+        let parents: Vec<Commit> = if first_parent {
+            commit.parents().take(1).collect()
+        } else {
+            commit.parents().collect()
+        };
+
+        if parents.is_empty() {
+            return Ok((own.is_some(), None));
+        }
+
+        let mut touched = true;
+        for parent in &parents {
+            if entry_id(parent)? == own {
+                touched = false;
+                break;
+            }
+        }
+
+        // The path appeared in this commit: look for the file it was renamed from
+        let mut renamed_from = None;
+        if touched && follow_renames && own.is_some() {
+            let first = &parents[0];
+            if entry_id(first)?.is_none() {
+                let mut diff = self.repo.diff_tree_to_tree(
+                    Some(&first.tree()?), Some(&commit.tree()?), None,
+                )?;
+                diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+                renamed_from = diff.deltas()
+                    .filter(|d| d.status() == git2::Delta::Renamed)
+                    .find(|d| d.new_file().path().and_then(|p| p.to_str()) == Some(path))
+                    .and_then(|d| d.old_file().path().and_then(|p| p.to_str()).map(|p| p.to_string()));
+            }
+        }
+
+        Ok((touched, renamed_from))
+    }
+}
+
+impl<'r> DateWalk<'r> {
+    fn new(repo: &'r Repository, starts: &[Oid], first_parent: bool) -> Result<Self> {
+        let mut walk = Self { repo, queue: BinaryHeap::new(), seen: HashSet::new(), next_seq: 0, first_parent };
+        for oid in starts {
+            walk.push(*oid)?;
+        }
+        Ok(walk)
+    }
+
+    fn push(&mut self, oid: Oid) -> Result<()> {
+        if self.seen.insert(oid) {
+            let time = self.repo.find_commit(oid)?.time().seconds();
+            self.queue.push((time, Reverse(self.next_seq), oid));
+            self.next_seq += 1;
+        }
+        Ok(())
+    }
+
+    fn next_commit(&mut self) -> Result<Option<Commit<'r>>> {
+        let Some((_, _, oid)) = self.queue.pop() else { return Ok(None) };
+        let commit = self.repo.find_commit(oid)?;
+        let parents: Vec<Oid> = commit.parent_ids()
+            .take(if self.first_parent { 1 } else { usize::MAX })
+            .collect();
+        for parent in parents {
+            self.push(parent)?;
+        }
+        Ok(Some(commit))
+    }
+
+    /// Queued commits in the order they would be returned
+    fn frontier(&self) -> Vec<Oid> {
+        let mut queued = self.queue.clone().into_sorted_vec();
+        queued.reverse();
+        queued.into_iter().map(|(_, _, oid)| oid).collect()
+    }
+}
+
+impl LogFilter {
+    fn new(query: &CommitQuery) -> Result<Self> {
+        let pattern = |value: &Option<String>, what: &str| -> Result<Option<Regex>> {
+            match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                Some(p) => Ok(Some(
+                    RegexBuilder::new(p)
+                        .case_insensitive(true)
+                        .build()
+                        .context(format!("Invalid {} pattern: {}", what, p))?,
+                )),
+                None => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            author: pattern(&query.author, "author")?,
+            committer: pattern(&query.committer, "committer")?,
+            grep: pattern(&query.grep, "message")?,
+            since: query.since.as_deref().map(|d| parse_date(d, false)).transpose()?,
+            until: query.until.as_deref().map(|d| parse_date(d, true)).transpose()?,
+        })
+    }
+
+    fn matches(&self, commit: &Commit) -> bool {
+        let identity_matches = |re: &Option<Regex>, sig: git2::Signature| match re {
+            Some(re) => {
+                re.is_match(&String::from_utf8_lossy(sig.name_bytes()))
+                    || re.is_match(&String::from_utf8_lossy(sig.email_bytes()))
+            }
+            None => true,
+        };
+
+        // --since/--until filter on the committer date, as git does
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+            return false;
+        }
+
+        identity_matches(&self.author, commit.author())
+            && identity_matches(&self.committer, commit.committer())
+            && self.grep.as_ref()
+                .is_none_or(|re| re.is_match(&String::from_utf8_lossy(commit.message_bytes())))
+    }
+}
+
+/// Parse an RFC 3339 timestamp or a `YYYY-MM-DD` date (start or end of that day, UTC)
+fn parse_date(value: &str, end_of_day: bool) -> Result<i64> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp());
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .context(format!("Invalid date: {}", value))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.map(|t| t.and_utc().timestamp()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Repository, Signature, Time};
+
+    /// Two branches with interleaved commit times merged back together, five times over
+    fn branchy_repo() -> (tempfile::TempDir, GitRepository) {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = Repository::init(dir.path()).unwrap();
+        let tree = {
+            let mut index = raw.index().unwrap();
+            raw.find_tree(index.write_tree().unwrap()).unwrap()
+        };
+        let mut time = 1_000_000;
+        let mut commit = |parents: &[Oid], message: &str| -> Oid {
+            time += 60;
+            let sig = Signature::new("Test", "test@example.com", &Time::new(time, 0)).unwrap();
+            let parents: Vec<Commit> = parents.iter().map(|p| raw.find_commit(*p).unwrap()).collect();
+            let parents: Vec<&Commit> = parents.iter().collect();
+            raw.commit(None, &sig, &sig, message, &tree, &parents).unwrap()
+        };
+
+        let mut tip = commit(&[], "root");
+        for round in 0..5 {
+            let side = commit(&[tip], &format!("side {}", round));
+            let main = commit(&[tip], &format!("main {}", round));
+            let side = commit(&[side], &format!("side {} again", round));
+            tip = commit(&[main, side], &format!("merge {}", round));
+        }
+        raw.reference("refs/heads/main", tip, true, "test").unwrap();
+        raw.set_head("refs/heads/main").unwrap();
+
+        let repo = GitRepository::open(dir.path().to_str().unwrap()).unwrap();
+        (dir, repo)
+    }
+
+    fn all_pages(repo: &GitRepository, query: CommitQuery) -> Vec<String> {
+        let mut query = CommitQuery { limit: Some(3), ..query };
+        let mut hashes = Vec::new();
+        loop {
+            let page = repo.query_commits(&query).unwrap();
+            hashes.extend(page.commits.into_iter().map(|c| c.hash));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        hashes
+    }
+
+    #[test]
+    fn test_date_order_pages_match_full_walk() {
+        let (_dir, repo) = branchy_repo();
+        let full = repo.query_commits(&CommitQuery { limit: Some(1000), ..Default::default() }).unwrap();
+        let full: Vec<String> = full.commits.into_iter().map(|c| c.hash).collect();
+        assert_eq!(full.len(), 21);
+        assert_eq!(all_pages(&repo, CommitQuery::default()), full);
+    }
+
+    #[test]
+    fn test_topo_order_pages_match_full_walk() {
+        let (_dir, repo) = branchy_repo();
+        let query = CommitQuery { order: Some("topo".to_string()), ..Default::default() };
+        let full = repo.query_commits(&CommitQuery { limit: Some(1000), ..query.clone() }).unwrap();
+        let full: Vec<String> = full.commits.into_iter().map(|c| c.hash).collect();
+        assert_eq!(all_pages(&repo, query), full);
+    }
+
+    #[test]
+    fn test_first_parent_pages_skip_side_branches() {
+        let (_dir, repo) = branchy_repo();
+        let query = CommitQuery { first_parent: true, ..Default::default() };
+        let hashes = all_pages(&repo, query);
+        // root, then main + merge per round
+        assert_eq!(hashes.len(), 11);
+    }
+
+    #[test]
+    fn test_date_order_keeps_children_first_on_equal_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = Repository::init(dir.path()).unwrap();
+        let tree = {
+            let mut index = raw.index().unwrap();
+            raw.find_tree(index.write_tree().unwrap()).unwrap()
+        };
+        let sig = Signature::new("Test", "test@example.com", &Time::new(1_000_000, 0)).unwrap();
+        let mut expected = Vec::new();
+        let mut parent: Option<Oid> = None;
+        for i in 0..7 {
+            let parents: Vec<Commit> = parent.iter().map(|p| raw.find_commit(*p).unwrap()).collect();
+            let parents: Vec<&Commit> = parents.iter().collect();
+            let oid = raw.commit(None, &sig, &sig, &format!("c{}", i), &tree, &parents).unwrap();
+            expected.insert(0, oid.to_string());
+            parent = Some(oid);
+        }
+        raw.reference("refs/heads/main", parent.unwrap(), true, "test").unwrap();
+        raw.set_head("refs/heads/main").unwrap();
+
+        let repo = GitRepository::open(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(all_pages(&repo, CommitQuery::default()), expected);
+    }
+}
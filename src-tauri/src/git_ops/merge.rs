@@ -1,6 +1,6 @@
 //! Merge operations
 //!
-//! This module handles all merge-related Git operations including cherry-pick and conflict resolution.
+//! This module handles all merge-related Git operations including cherry-pick, revert and conflict resolution.
 
 use anyhow::{Context, Result};
 
@@ -10,6 +10,7 @@ use super::types::ConflictInfo;
 impl GitRepository {
     /// Merge a branch into the current branch
     pub fn merge(&self, branch_name: &str) -> Result<String> {
+        self.ensure_no_rebase_in_progress("merge")?;
         let (their_commit, _) = self.repo.revparse_ext(branch_name)?;
         let annotated_commit = self.repo.find_annotated_commit(their_commit.id())?;
 
@@ -21,7 +22,8 @@ impl GitRepository {
             let refname = format!("refs/heads/{}", self.get_current_branch()?);
             let mut reference = self.repo.find_reference(&refname)?;
             reference.set_target(annotated_commit.id(), "Fast-forward merge")?;
-            self.repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            self.force_checkout_head()?;
+            self.run_post_hook("post-merge", &["0"]);
             return Ok("Fast-forward merge completed".to_string());
         } else {
             self.repo.merge(&[&annotated_commit], None, None)?;
@@ -38,7 +40,7 @@ impl GitRepository {
             let their_commit = self.repo.find_commit(annotated_commit.id())?;
 
             let message = format!("Merge branch '{}'", branch_name);
-            self.repo.commit(
+            self.create_commit(
                 Some("HEAD"),
                 &signature,
                 &signature,
@@ -46,6 +48,7 @@ impl GitRepository {
                 &tree,
                 &[&head_commit, &their_commit],
             )?;
+            self.run_post_hook("post-merge", &["0"]);
 
             return Ok("Merge completed successfully".to_string());
         }
@@ -53,6 +56,7 @@ impl GitRepository {
 
     /// Cherry-pick a single commit
     pub fn cherry_pick(&self, commit_hash: &str) -> Result<String> {
+        self.ensure_no_rebase_in_progress("cherry-pick")?;
         let oid = git2::Oid::from_str(commit_hash)
             .context(format!("Invalid commit hash: {}", commit_hash))?;
         let commit = self.repo.find_commit(oid)
@@ -73,7 +77,7 @@ impl GitRepository {
         let head = self.repo.head()?.peel_to_commit()?;
 
         let message = commit.message().unwrap_or("Cherry-picked commit");
-        self.repo.commit(
+        self.create_commit(
             Some("HEAD"),
             &signature,
             &signature,
@@ -102,6 +106,172 @@ impl GitRepository {
         Ok(results)
     }
 
+    /// Revert a commit
+    ///
+    /// `mainline` selects the parent (1-based) a merge commit is reverted against.
+    /// With `no_commit` the revert is only applied to the index and working directory.
+    /// On conflicts the revert stays in progress until `revert_continue` or `revert_abort`.
+    pub fn revert(&self, commit_hash: &str, mainline: Option<u32>, no_commit: bool) -> Result<String> {
+        self.ensure_no_rebase_in_progress("revert")?;
+        let commit = self.repo.revparse_single(commit_hash)
+            .context(format!("Failed to find commit: {}", commit_hash))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", commit_hash))?;
+        let short_id = commit.id().to_string()[..7].to_string();
+
+        let mut opts = git2::RevertOptions::new();
+        match mainline {
+            Some(parent) if parent == 0 || parent as usize > commit.parent_count() => {
+                anyhow::bail!("Commit {} has no parent {}", short_id, parent);
+            }
+            Some(parent) => {
+                opts.mainline(parent);
+            }
+            None if commit.parent_count() > 1 => {
+                anyhow::bail!("Commit {} is a merge; choose the mainline parent to revert against", short_id);
+            }
+            None => {}
+        }
+
+        if no_commit && self.has_staged_changes()? {
+            return self.revert_onto_index(&commit, mainline.unwrap_or(0), &short_id);
+        }
+
+        self.repo.revert(&commit, Some(&mut opts))
+            .context("Revert failed")?;
+
+        let index = self.repo.index()?;
+        if index.has_conflicts() {
+            return Ok("Revert has conflicts - needs resolution".to_string());
+        }
+
+        if no_commit {
+            // Like `git revert --no-commit`: keep the changes, drop the revert state
+            self.repo.cleanup_state()?;
+            return Ok(format!("Revert of {} staged", short_id));
+        }
+
+        self.commit_revert()?;
+        Ok(format!("Revert successful: {}", short_id))
+    }
+
+    /// Revert multiple commits in order, stopping at the first failure or conflict
+    pub fn revert_batch(&self, commit_hashes: Vec<String>, mainline: Option<u32>, no_commit: bool) -> Result<Vec<String>> {
+        let mut results = Vec::new();
+
+        for hash in commit_hashes {
+            let commit = self.repo.revparse_single(&hash)
+                .and_then(|obj| obj.peel_to_commit())
+                .ok();
+            let short = commit.as_ref()
+                .map(|commit| commit.id().to_string()[..7].to_string())
+                .unwrap_or_else(|| hash.clone());
+            // The mainline only applies to the merge commits of the batch
+            let is_merge = commit.as_ref().is_none_or(|commit| commit.parent_count() > 1);
+            match self.revert(&hash, mainline.filter(|_| is_merge), no_commit) {
+                Ok(msg) => {
+                    let conflicted = self.repo.index()?.has_conflicts();
+                    results.push(format!("{}: {}", short, msg));
+                    if conflicted {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    results.push(format!("{}: Failed - {}", short, e));
+                    break;
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Commit an in-progress revert once its conflicts are resolved
+    pub fn revert_continue(&self) -> Result<String> {
+        self.ensure_revert_in_progress()?;
+        if self.repo.index()?.has_conflicts() {
+            anyhow::bail!("Resolve all conflicts before continuing the revert");
+        }
+
+        let oid = self.commit_revert()?;
+        Ok(format!("Revert successful: {}", &oid.to_string()[..7]))
+    }
+
+    /// Abort an in-progress revert, restoring HEAD
+    pub fn revert_abort(&self) -> Result<()> {
+        self.ensure_revert_in_progress()?;
+
+        let head = self.repo.head()?.peel_to_commit()?;
+        self.reset_head(&head, git2::ResetType::Hard)?;
+        self.repo.cleanup_state()?;
+        Ok(())
+    }
+
+    /// Revert on top of already staged changes, as `git revert --no-commit` does for a series
+    ///
+    /// libgit2's revert refuses to touch a dirty index, so the revert is merged in memory
+    /// against a temporary commit of the index and, if it is conflict-free, applied to the
+    /// index and working directory on top of the staged changes.
+    fn revert_onto_index(&self, commit: &git2::Commit, mainline: u32, short_id: &str) -> Result<String> {
+        let mut index = self.repo.index()?;
+        let staged_tree = self.repo.find_tree(index.write_tree()?)?;
+        let head = self.repo.head()?.peel_to_commit()?;
+        let signature = self.repo.signature()?;
+        // Unreferenced object; it only gives revert_commit a commit to merge into
+        let staged_commit = self.repo.find_commit(self.repo.commit(
+            None, &signature, &signature, "staged changes", &staged_tree, &[&head],
+        )?)?;
+
+        let mut reverted = self.repo.revert_commit(commit, &staged_commit, mainline, None)?;
+        if reverted.has_conflicts() {
+            anyhow::bail!("Reverting {} conflicts with the changes staged so far", short_id);
+        }
+
+        let tree = self.repo.find_tree(reverted.write_tree_to(&self.repo)?)?;
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts.show_binary(true);
+        let diff = self.repo.diff_tree_to_tree(Some(&staged_tree), Some(&tree), Some(&mut diff_opts))?;
+        self.repo.apply(&diff, git2::ApplyLocation::Both, None)
+            .context("Revert failed")?;
+        Ok(format!("Revert of {} staged", short_id))
+    }
+
+    fn has_staged_changes(&self) -> Result<bool> {
+        let head_tree = self.repo.head()?.peel_to_tree()?;
+        let diff = self.repo.diff_tree_to_index(Some(&head_tree), None, None)?;
+        Ok(diff.deltas().len() > 0)
+    }
+
+    fn ensure_revert_in_progress(&self) -> Result<()> {
+        match self.repo.state() {
+            git2::RepositoryState::Revert | git2::RepositoryState::RevertSequence => Ok(()),
+            _ => anyhow::bail!("No revert in progress"),
+        }
+    }
+
+    /// Commit the index with the revert message prepared in MERGE_MSG
+    fn commit_revert(&self) -> Result<git2::Oid> {
+        let message = self.repo.message()
+            .context("Failed to read the revert message")?;
+
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let signature = self.repo.signature()?;
+        let head = self.repo.head()?.peel_to_commit()?;
+
+        let oid = self.create_commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &[&head],
+        )?;
+        self.repo.cleanup_state()?;
+        Ok(oid)
+    }
+
     /// Get all merge conflicts
     pub fn get_conflicts(&self) -> Result<Vec<ConflictInfo>> {
         use std::fs;
@@ -205,7 +375,7 @@ impl GitRepository {
     /// Abort the current merge
     pub fn abort_merge(&self) -> Result<()> {
         let head = self.repo.head()?.peel_to_commit()?;
-        self.repo.reset(head.as_object(), git2::ResetType::Hard, None)?;
+        self.reset_head(&head, git2::ResetType::Hard)?;
 
         let repo_path = self.repo.path();
         let merge_head = repo_path.join("MERGE_HEAD");
@@ -221,3 +391,24 @@ impl GitRepository {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{commit_file, init_repo};
+
+    #[test]
+    fn test_revert_batch_labels_by_commit_id() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "f.txt", "one\n", "base");
+        let change = commit_file(&repo, "f.txt", "two\n", "change");
+        let target = repo.repo.revparse_single(&change).unwrap();
+        repo.repo.tag_lightweight("v1.0-测试", &target, false).unwrap();
+
+        let results = repo.revert_batch(vec!["v1.0-测试".to_string()], None, false).unwrap();
+        assert_eq!(results, vec![format!("{}: Revert successful: {}", &change[..7], &change[..7])]);
+        assert_eq!(std::fs::read_to_string(dir.path().join("f.txt")).unwrap(), "one\n");
+
+        let results = repo.revert_batch(vec!["missing-测试".to_string()], None, false).unwrap();
+        assert!(results[0].starts_with("missing-测试: Failed"));
+    }
+}
@@ -7,12 +7,37 @@ mod types;
 mod repository;
 mod branch;
 mod remote;
+mod credentials;
+mod credential_helper;
+mod clone;
+mod init;
 mod diff;
+mod inline_diff;
 mod stash;
 mod tag;
 mod merge;
 mod blame;
+mod hunk;
+mod rebase;
+mod signing;
+mod hooks;
+mod log;
+mod patch;
+mod reset;
+mod reflog;
+mod undo;
+mod recover;
+mod bisect;
+mod worktree;
+mod submodule;
+mod lfs;
+mod sparse;
+mod inspect;
+#[cfg(test)]
+mod test_support;
 
 // Re-export all public types and structs
 pub use types::*;
 pub use repository::GitRepository;
+pub use hooks::HookOptions;
+pub use credential_helper::{run_keychain_helper, set_keychain_helper};
@@ -0,0 +1,419 @@
+//! Patch export and import
+//!
+//! This module provides `format-patch` style export of a commit range as
+//! mbox patches, `git apply` style application of a patch to the working
+//! directory and/or index, and `git am` style import of an mbox series as
+//! commits.
+
+use std::path::Path;
+use git2::{ApplyLocation, ApplyOptions, Diff, DiffFindOptions, DiffOptions, Email, EmailCreateOptions, Signature, Sort, Time};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::DateTime;
+
+use super::repository::GitRepository;
+use super::types::PatchFile;
+
+/// Longest subject slug used in patch file names, as format-patch does
+const MAX_SLUG_LEN: usize = 52;
+
+/// One message of an mbox series
+struct MailPatch {
+    author_name: String,
+    author_email: String,
+    date: Option<Time>,
+    subject: String,
+    message: String,
+    diff: Vec<u8>,
+}
+
+impl GitRepository {
+    /// Export the commits in `from..to` as mbox patches, oldest first
+    ///
+    /// Merge commits are skipped like `git format-patch` does. When `output_dir` is
+    /// given, every patch is also written there as `NNNN-subject.patch`.
+    pub fn format_patch(&self, from: &str, to: &str, output_dir: Option<&str>) -> Result<Vec<PatchFile>> {
+        let to_commit = self.repo.revparse_single(to)
+            .context(format!("无法找到引用: {}", to))?
+            .peel_to_commit()?;
+        let from_commit = self.repo.revparse_single(from)
+            .context(format!("无法找到引用: {}", from))?
+            .peel_to_commit()?;
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push(to_commit.id())?;
+        revwalk.hide(from_commit.id())?;
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if commit.parent_count() <= 1 {
+                commits.push(commit);
+            }
+        }
+
+        let total = commits.len();
+        let mut patches = Vec::new();
+        for (i, commit) in commits.iter().enumerate() {
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
+            };
+            let mut diff_opts = DiffOptions::new();
+            diff_opts.show_binary(true);
+            let mut diff = self.repo.diff_tree_to_tree(
+                parent_tree.as_ref(), Some(&commit.tree()?), Some(&mut diff_opts),
+            )?;
+            diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+            let summary = commit.summary().unwrap_or("");
+            let body = commit.body().unwrap_or("");
+            let email = Email::from_diff(
+                &diff,
+                i + 1,
+                total,
+                &commit.id(),
+                summary,
+                body,
+                &commit.author(),
+                &mut EmailCreateOptions::new(),
+            )
+            .context(format!("Failed to format patch for {}", commit.id()))?;
+
+            patches.push(PatchFile {
+                file_name: format!("{:04}-{}.patch", i + 1, subject_slug(summary)),
+                commit: commit.id().to_string(),
+                content: String::from_utf8_lossy(email.as_slice()).to_string(),
+            });
+        }
+
+        if let Some(dir) = output_dir {
+            let dir = Path::new(dir);
+            std::fs::create_dir_all(dir)
+                .context(format!("Failed to create directory: {}", dir.display()))?;
+            for patch in &patches {
+                std::fs::write(dir.join(&patch.file_name), &patch.content)
+                    .context(format!("Failed to write {}", patch.file_name))?;
+            }
+        }
+
+        Ok(patches)
+    }
+
+    /// Apply a patch file like `git apply`, returning the affected paths
+    ///
+    /// `location` is `"workdir"`, `"index"` (`--cached`) or `"both"` (`--index`).
+    /// With `check_only` nothing is changed; an error means the patch does not apply.
+    pub fn apply_patch(&self, patch_path: &str, location: &str, check_only: bool) -> Result<Vec<String>> {
+        let content = std::fs::read(patch_path)
+            .context(format!("Failed to read patch: {}", patch_path))?;
+        let diff = Diff::from_buffer(diff_section(&content))
+            .context("Failed to parse patch")?;
+
+        let location = match location {
+            "workdir" => ApplyLocation::WorkDir,
+            "index" => ApplyLocation::Index,
+            "both" => ApplyLocation::Both,
+            other => anyhow::bail!("Unknown apply location: {}", other),
+        };
+
+        let mut apply_opts = ApplyOptions::new();
+        apply_opts.check(check_only);
+        self.repo.apply(&diff, location, Some(&mut apply_opts))
+            .context("Patch does not apply")?;
+
+        Ok(diff.deltas()
+            .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Apply an mbox series as commits like `git am`, returning the new commit ids
+    ///
+    /// Stops at the first patch that does not apply; commits created before it are kept.
+    pub fn apply_mailbox(&self, mbox_path: &str) -> Result<Vec<String>> {
+        self.ensure_clean_worktree("apply patches")?;
+
+        let content = std::fs::read(mbox_path)
+            .context(format!("Failed to read mailbox: {}", mbox_path))?;
+        let mails = split_mbox(&String::from_utf8_lossy(&content))
+            .iter()
+            .map(|mail| parse_mail(mail))
+            .collect::<Result<Vec<_>>>()?;
+        if mails.is_empty() {
+            anyhow::bail!("No patches found in {}", mbox_path);
+        }
+
+        let committer = self.repo.signature()?;
+        let total = mails.len();
+        let mut created = Vec::new();
+
+        for (i, mail) in mails.iter().enumerate() {
+            let failed = |what: &str| {
+                format!(
+                    "Patch {}/{} ({}) {}; {} earlier patch(es) were committed",
+                    i + 1, total, mail.subject, what, created.len()
+                )
+            };
+
+            let diff = Diff::from_buffer(&mail.diff).context(failed("could not be parsed"))?;
+            self.repo.apply(&diff, ApplyLocation::Both, None)
+                .context(failed("does not apply"))?;
+
+            let mut index = self.repo.index()?;
+            index.read(false)?;
+            let tree = self.repo.find_tree(index.write_tree()?)?;
+
+            let author = match mail.date {
+                Some(time) => Signature::new(&mail.author_name, &mail.author_email, &time)?,
+                None => Signature::now(&mail.author_name, &mail.author_email)?,
+            };
+            let parent = match self.repo.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(_) => None,
+            };
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+            let oid = self.create_commit(Some("HEAD"), &author, &committer, &mail.message, &tree, &parents)?;
+            created.push(oid.to_string());
+        }
+
+        Ok(created)
+    }
+}
+
+/// File name slug of a subject, following format-patch's rules
+fn subject_slug(subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= MAX_SLUG_LEN {
+            break;
+        }
+    }
+
+    let slug = slug.trim_end_matches(['-', '.']).to_string();
+    if slug.is_empty() { "patch".to_string() } else { slug }
+}
+
+/// The part of a patch file libgit2 can parse: from the first `diff --git` line on
+fn diff_section(content: &[u8]) -> &[u8] {
+    if content.starts_with(b"diff --git ") {
+        return content;
+    }
+    content.windows(12)
+        .position(|w| w == b"\ndiff --git ")
+        .map(|pos| &content[pos + 1..])
+        .unwrap_or(content)
+}
+
+/// Split an mbox into messages at `From ` separator lines
+fn split_mbox(content: &str) -> Vec<String> {
+    let mut mails = Vec::new();
+    let mut current = String::new();
+    let mut previous_blank = true;
+
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("From ") && previous_blank {
+            if !current.trim().is_empty() {
+                mails.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            previous_blank = false;
+            continue;
+        }
+        previous_blank = line.trim_end_matches(['\r', '\n']).is_empty();
+        current.push_str(line);
+    }
+    if !current.trim().is_empty() {
+        mails.push(current);
+    }
+
+    mails
+}
+
+/// Parse one patch email into author, message and diff
+fn parse_mail(mail: &str) -> Result<MailPatch> {
+    let mail = mail.replace("\r\n", "\n");
+    let (header_text, body) = mail.split_once("\n\n").unwrap_or((mail.as_str(), ""));
+
+    // Unfold continuation lines before reading the headers
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in header_text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    let header = |name: &str| {
+        headers.iter().find(|(n, _)| n == name).map(|(_, v)| decode_header(v))
+    };
+
+    let from = header("from").ok_or_else(|| anyhow::anyhow!("Patch email has no From header"))?;
+    let (author_name, author_email) = match from.rsplit_once('<') {
+        Some((name, email)) => (
+            name.trim().trim_matches('"').to_string(),
+            email.trim_end_matches('>').trim().to_string(),
+        ),
+        None => (from.trim().to_string(), from.trim().to_string()),
+    };
+
+    let date = header("date")
+        .and_then(|d| DateTime::parse_from_rfc2822(&d).ok())
+        .map(|d| Time::new(d.timestamp(), d.offset().local_minus_utc() / 60));
+
+    let subject = strip_subject_prefix(&header("subject").unwrap_or_default());
+
+    let diff_start = body.find("\ndiff --git ")
+        .map(|pos| pos + 1)
+        .or_else(|| body.starts_with("diff --git ").then_some(0))
+        .ok_or_else(|| anyhow::anyhow!("Patch \"{}\" contains no diff", subject))?;
+
+    // The message ends at the `---` line that precedes the diffstat
+    let description = body[..diff_start]
+        .lines()
+        .take_while(|line| *line != "---")
+        .collect::<Vec<_>>()
+        .join("\n");
+    let description = description.trim();
+
+    let mut message = subject.clone();
+    if !description.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(description);
+    }
+    message.push('\n');
+
+    Ok(MailPatch {
+        author_name,
+        author_email,
+        date,
+        subject,
+        message,
+        diff: body.as_bytes()[diff_start..].to_vec(),
+    })
+}
+
+/// Remove `[PATCH n/m]` style tags and `Re:` prefixes from a subject
+fn strip_subject_prefix(subject: &str) -> String {
+    let mut subject = subject.trim();
+    loop {
+        if subject.starts_with('[') {
+            if let Some(end) = subject.find(']') {
+                subject = subject[end + 1..].trim_start();
+                continue;
+            }
+        }
+        if subject.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:")) {
+            subject = subject[3..].trim_start();
+            continue;
+        }
+        return subject.to_string();
+    }
+}
+
+/// Decode RFC 2047 encoded words (`=?UTF-8?q?...?=` / `=?UTF-8?b?...?=`) in a header
+fn decode_header(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+
+    while let Some(start) = rest.find("=?") {
+        let before = &rest[..start];
+        match decode_encoded_word(&rest[start..]) {
+            Some((text, consumed)) => {
+                // Whitespace between two encoded words is not part of the text
+                if !(after_word && before.trim().is_empty()) {
+                    result.push_str(before);
+                }
+                result.push_str(&text);
+                rest = &rest[start + consumed..];
+                after_word = true;
+            }
+            None => {
+                result.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Decode one encoded word at the start of `input`, returning the text and its length
+fn decode_encoded_word(input: &str) -> Option<(String, usize)> {
+    let inner = input.strip_prefix("=?")?;
+    let (_charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let text = &inner[..end];
+    let consumed = input.len() - inner.len() + end + 2;
+
+    let bytes = match encoding {
+        "b" | "B" => BASE64.decode(text).ok()?,
+        "q" | "Q" => {
+            let mut bytes = Vec::new();
+            let mut chars = text.bytes();
+            while let Some(b) = chars.next() {
+                match b {
+                    b'_' => bytes.push(b' '),
+                    b'=' => {
+                        let hex = [chars.next()?, chars.next()?];
+                        bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+                    }
+                    _ => bytes.push(b),
+                }
+            }
+            bytes
+        }
+        _ => return None,
+    };
+
+    Some((String::from_utf8_lossy(&bytes).to_string(), consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+
+    #[test]
+    fn test_strip_subject_prefix() {
+        assert_eq!(strip_subject_prefix("[PATCH 1/2] Re: fix"), "fix");
+        assert_eq!(strip_subject_prefix("abé change"), "abé change");
+        assert_eq!(strip_subject_prefix("é"), "é");
+    }
+
+    #[test]
+    fn test_format_patch_round_trip() {
+        let (dir, repo) = init_repo();
+        let base = commit_file(&repo, "f.txt", "one\n", "base");
+        commit_file(&repo, "f.txt", "one\ntwo\n", "abé change");
+
+        let out = dir.path().join("patches");
+        let patches = repo.format_patch(&base, "HEAD", out.to_str()).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].file_name, "0001-ab-change.patch");
+
+        let base = repo.repo.revparse_single(&base).unwrap();
+        repo.repo.reset(&base, git2::ResetType::Hard, None).unwrap();
+        let created = repo.apply_mailbox(out.join(&patches[0].file_name).to_str().unwrap()).unwrap();
+        assert_eq!(created.len(), 1);
+
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.summary(), Some("abé change"));
+        assert_eq!(head.author().email(), Some("test@example.com"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("f.txt")).unwrap(), "one\ntwo\n");
+    }
+}
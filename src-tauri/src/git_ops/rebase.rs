@@ -0,0 +1,898 @@
+//! Interactive rebase operations
+//!
+//! This module implements a rebase sequencer driven by a todo list
+//! (pick/reword/edit/squash/fixup/drop, in any order). libgit2's own rebase
+//! always replays commits in their original order, so each step is applied
+//! with cherry-pick on a detached HEAD and the branch is only moved once the
+//! whole plan has been replayed. State is persisted in the git directory so
+//! that continue/skip/abort work across commands; `git` itself does not see it,
+//! so the other operations here that move HEAD refuse to run meanwhile. Reword
+//! of older commits and `--autosquash` style fixups are built on the same sequencer.
+
+use std::path::PathBuf;
+use git2::{Oid, Sort, Status};
+use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+
+use super::repository::GitRepository;
+use super::types::{RebaseTodoItem, RebaseStatus};
+
+/// File inside the git directory holding the in-progress rebase state
+const REBASE_STATE_FILE: &str = "caogit-rebase.json";
+
+/// Changes staged in the index
+const STAGED_CHANGES: Status = Status::INDEX_MODIFIED
+    .union(Status::INDEX_NEW)
+    .union(Status::INDEX_DELETED)
+    .union(Status::INDEX_RENAMED);
+
+/// Changes to tracked files not staged yet
+const UNSTAGED_CHANGES: Status = Status::WT_MODIFIED
+    .union(Status::WT_DELETED)
+    .union(Status::WT_TYPECHANGE);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RebaseAction {
+    Pick,
+    Reword,
+    Edit,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    fn parse(action: &str) -> Result<Self> {
+        match action {
+            "pick" | "p" => Ok(Self::Pick),
+            "reword" | "r" => Ok(Self::Reword),
+            "edit" | "e" => Ok(Self::Edit),
+            "squash" | "s" => Ok(Self::Squash),
+            "fixup" | "f" => Ok(Self::Fixup),
+            "drop" | "d" => Ok(Self::Drop),
+            _ => anyhow::bail!("Unknown rebase action: {}", action),
+        }
+    }
+}
+
+/// Persisted state of an in-progress rebase
+#[derive(Debug, Serialize, Deserialize)]
+struct RebaseState {
+    head_name: Option<String>,
+    orig_head: String,
+    onto: String,
+    total: usize,
+    todo: Vec<RebaseTodoItem>,
+    current: Option<RebaseTodoItem>,
+    stopped_for_edit: bool,
+    #[serde(default)]
+    skipped: Vec<String>,
+}
+
+/// Result of replaying a single todo item
+enum StepOutcome {
+    Applied,
+    Conflicts,
+    StoppedForEdit,
+}
+
+impl GitRepository {
+    /// Build the default todo list for rebasing the current branch onto `upstream`
+    ///
+    /// Commits are returned oldest first; merge commits are left out, as with `git rebase -i`.
+    pub fn get_rebase_plan(&self, upstream: &str) -> Result<Vec<RebaseTodoItem>> {
+        let upstream_commit = self.repo.revparse_single(upstream)
+            .context(format!("无法找到引用: {}", upstream))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", upstream))?;
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.hide(upstream_commit.id())?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+        let mut items = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+
+            items.push(RebaseTodoItem {
+                action: "pick".to_string(),
+                commit: commit.id().to_string(),
+                summary: commit.summary().unwrap_or("").to_string(),
+                message: None,
+            });
+        }
+
+        Ok(items)
+    }
+
+    /// Start an interactive rebase of the current branch onto `onto` using the given todo list
+    pub fn rebase_interactive(&self, onto: &str, items: Vec<RebaseTodoItem>) -> Result<RebaseStatus> {
+        if self.rebase_in_progress() {
+            anyhow::bail!("A rebase is already in progress. Continue, skip or abort it first.");
+        }
+        if self.repo.state() != git2::RepositoryState::Clean {
+            anyhow::bail!("Cannot rebase: another operation (merge, cherry-pick, ...) is in progress");
+        }
+        self.ensure_clean_worktree("rebase")?;
+        Self::validate_rebase_plan(&items)?;
+
+        let head = self.repo.head()?;
+        let head_name = if self.repo.head_detached()? {
+            None
+        } else {
+            head.name().map(|s| s.to_string())
+        };
+        let orig_head = head.peel_to_commit()?;
+
+        let onto_commit = self.repo.revparse_single(onto)
+            .context(format!("无法找到引用: {}", onto))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", onto))?;
+
+        // Resolve every commit up front so a typo does not leave a half-done rebase behind
+        let mut todo = Vec::with_capacity(items.len());
+        for mut item in items {
+            let commit = self.repo.revparse_single(&item.commit)
+                .context(format!("Failed to find commit: {}", item.commit))?
+                .peel_to_commit()?;
+            if commit.parent_count() > 1 {
+                anyhow::bail!("Merge commits cannot be rebased: {}", item.commit);
+            }
+            item.commit = commit.id().to_string();
+            if item.summary.is_empty() {
+                item.summary = commit.summary().unwrap_or("").to_string();
+            }
+            todo.push(item);
+        }
+
+        self.checkout_detached(&onto_commit, None)
+            .context("Failed to check out rebase base")?;
+
+        let state = RebaseState {
+            head_name,
+            orig_head: orig_head.id().to_string(),
+            onto: onto_commit.id().to_string(),
+            total: todo.len(),
+            todo,
+            current: None,
+            stopped_for_edit: false,
+            skipped: Vec::new(),
+        };
+
+        self.run_rebase(state)
+    }
+
+    /// Continue a rebase after resolving conflicts or editing a commit
+    pub fn rebase_continue(&self) -> Result<RebaseStatus> {
+        let mut state = self.load_rebase_state()?;
+
+        if state.current.is_some() && self.repo.index()?.has_conflicts() {
+            anyhow::bail!("Resolve all conflicts before continuing the rebase");
+        }
+        // Only the index is committed; a later failed step would reset unstaged edits away
+        if self.has_uncommitted_changes(UNSTAGED_CHANGES)? {
+            anyhow::bail!(
+                "Cannot continue the rebase: You have unstaged changes. \
+                Please stage or stash them first."
+            );
+        }
+
+        if let Some(item) = state.current.take() {
+            if state.stopped_for_edit {
+                self.amend_head_with_index()?;
+            } else {
+                let action = RebaseAction::parse(&item.action)?;
+                if !self.commit_rebase_item(&item)? {
+                    state.skipped.push(item.commit.clone());
+                }
+                if action == RebaseAction::Edit {
+                    state.current = Some(item);
+                    state.stopped_for_edit = true;
+                    self.save_rebase_state(&state)?;
+                    return Ok(self.rebase_status_from(&state, "stopped"));
+                }
+            }
+            state.stopped_for_edit = false;
+        }
+
+        self.run_rebase(state)
+    }
+
+    /// Skip the commit the rebase stopped at and continue with the rest of the plan
+    pub fn rebase_skip(&self) -> Result<RebaseStatus> {
+        let mut state = self.load_rebase_state()?;
+
+        if state.stopped_for_edit && self.has_uncommitted_changes(STAGED_CHANGES | UNSTAGED_CHANGES)? {
+            anyhow::bail!(
+                "Cannot skip: You have uncommitted changes. \
+                Please commit or stash your changes first."
+            );
+        }
+        if !state.stopped_for_edit {
+            let head = self.repo.head()?.peel_to_commit()?;
+            self.reset_head(&head, git2::ResetType::Hard)?;
+            self.repo.cleanup_state()?;
+        }
+        // After a failed step the item waits at the front of the todo list
+        if state.current.take().is_none() && !state.todo.is_empty() {
+            state.todo.remove(0);
+        }
+        state.stopped_for_edit = false;
+
+        self.run_rebase(state)
+    }
+
+    /// Abort the rebase and restore the original branch
+    pub fn rebase_abort(&self) -> Result<()> {
+        let state = self.load_rebase_state()?;
+        let orig_head = self.repo.find_commit(Oid::from_str(&state.orig_head)?)?;
+
+        match &state.head_name {
+            Some(name) => self.repo.set_head(name)?,
+            None => self.repo.set_head_detached(orig_head.id())?,
+        }
+        self.reset_head(&orig_head, git2::ResetType::Hard)?;
+        self.repo.cleanup_state()?;
+
+        std::fs::remove_file(self.rebase_state_path())
+            .context("Failed to remove rebase state")?;
+        Ok(())
+    }
+
+    /// Get the status of the in-progress rebase
+    pub fn get_rebase_status(&self) -> Result<RebaseStatus> {
+        if !self.rebase_in_progress() {
+            return Ok(RebaseStatus {
+                state: "none".to_string(),
+                current: None,
+                done: 0,
+                total: 0,
+                head: None,
+                skipped: Vec::new(),
+            });
+        }
+
+        let state = self.load_rebase_state()?;
+        let label = match (&state.current, state.stopped_for_edit) {
+            (None, _) => "failed",
+            (Some(_), true) => "stopped",
+            (Some(_), false) => "conflicts",
+        };
+        Ok(self.rebase_status_from(&state, label))
+    }
+
+    /// Change the message of a commit on the current branch, replaying its descendants
+    pub fn reword_commit(&self, commit_hash: &str, message: &str) -> Result<RebaseStatus> {
+        if message.trim().is_empty() {
+            anyhow::bail!("Commit message cannot be empty");
+        }
+
+        let target = self.repo.revparse_single(commit_hash)
+            .context(format!("Failed to find commit: {}", commit_hash))?
+            .peel_to_commit()?;
+        let head = self.repo.head()?.peel_to_commit()?;
+
+        // Rewording HEAD only needs a new commit object, no replay
+        if target.id() == head.id() {
+            let parents: Vec<git2::Commit> = head.parents().collect();
+            let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+            let oid = self.create_commit(
+                Some("HEAD"),
+                &head.author(),
+                &self.repo.signature()?,
+                message,
+                &head.tree()?,
+                &parent_refs,
+            )?;
+            return Ok(RebaseStatus {
+                state: "completed".to_string(),
+                current: None,
+                done: 1,
+                total: 1,
+                head: Some(oid.to_string()),
+                skipped: Vec::new(),
+            });
+        }
+
+        if !self.repo.graph_descendant_of(head.id(), target.id())? {
+            anyhow::bail!("Commit {} is not on the current branch", commit_hash);
+        }
+        let parent = target.parent(0)
+            .context("Cannot reword the root commit while it has descendants")?;
+
+        // The rebase plan leaves merge commits out, which would silently flatten history
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(head.id())?;
+        revwalk.hide(parent.id())?;
+        for oid in revwalk {
+            if self.repo.find_commit(oid?)?.parent_count() > 1 {
+                anyhow::bail!("Cannot reword a commit that is followed by merge commits");
+            }
+        }
+
+        let base = parent.id().to_string();
+        let target_id = target.id().to_string();
+        let items = self.get_rebase_plan(&base)?
+            .into_iter()
+            .map(|mut item| {
+                if item.commit == target_id {
+                    item.action = "reword".to_string();
+                    item.message = Some(message.to_string());
+                }
+                item
+            })
+            .collect();
+
+        self.rebase_interactive(&base, items)
+    }
+
+    /// Create a `fixup!` or `squash!` commit for `target` from the current index
+    pub fn commit_fixup(&self, target: &str, squash: bool, message: Option<&str>) -> Result<String> {
+        let target_commit = self.repo.revparse_single(target)
+            .context(format!("Failed to find commit: {}", target))?
+            .peel_to_commit()?;
+
+        let prefix = if squash { "squash!" } else { "fixup!" };
+        let mut full_message = format!("{} {}", prefix, target_commit.summary().unwrap_or(""));
+        if let Some(body) = message.filter(|m| !m.trim().is_empty()) {
+            full_message.push_str("\n\n");
+            full_message.push_str(body);
+        }
+
+        self.commit(&full_message)
+    }
+
+    /// Rebase onto `upstream`, folding `fixup!`/`squash!` commits into their targets
+    pub fn rebase_autosquash(&self, upstream: &str) -> Result<RebaseStatus> {
+        let items = autosquash_plan(self.get_rebase_plan(upstream)?);
+        self.rebase_interactive(upstream, items)
+    }
+
+    /// Replay the remaining todo items, stopping on conflicts or edit requests
+    fn run_rebase(&self, mut state: RebaseState) -> Result<RebaseStatus> {
+        while !state.todo.is_empty() {
+            let item = state.todo.remove(0);
+
+            let outcome = match self.apply_rebase_item(&item, &mut state.skipped) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    // Nothing was committed for this item: drop any half-applied
+                    // changes and put it back, so continue retries it and skip drops it.
+                    // Every step starts from a clean tree, so only this step's changes are lost.
+                    if let Ok(head) = self.repo.head().and_then(|h| h.peel_to_commit()) {
+                        let _ = self.reset_head(&head, git2::ResetType::Hard);
+                    }
+                    let _ = self.repo.cleanup_state();
+                    state.todo.insert(0, item);
+                    state.current = None;
+                    state.stopped_for_edit = false;
+                    self.save_rebase_state(&state)?;
+                    return Err(e);
+                }
+            };
+
+            match outcome {
+                StepOutcome::Applied => {}
+                StepOutcome::Conflicts => {
+                    state.current = Some(item);
+                    state.stopped_for_edit = false;
+                    self.save_rebase_state(&state)?;
+                    return Ok(self.rebase_status_from(&state, "conflicts"));
+                }
+                StepOutcome::StoppedForEdit => {
+                    state.current = Some(item);
+                    state.stopped_for_edit = true;
+                    self.save_rebase_state(&state)?;
+                    return Ok(self.rebase_status_from(&state, "stopped"));
+                }
+            }
+        }
+
+        // All items replayed: move the branch to the rewritten history
+        let new_head = self.repo.head()?.peel_to_commit()?;
+        if let Some(name) = &state.head_name {
+            self.repo.reference(name, new_head.id(), true, "rebase (finish)")?;
+            self.repo.set_head(name)?;
+        }
+
+        let path = self.rebase_state_path();
+        if path.exists() {
+            std::fs::remove_file(path).context("Failed to remove rebase state")?;
+        }
+
+        state.current = None;
+        Ok(RebaseStatus {
+            state: "completed".to_string(),
+            current: None,
+            done: state.total,
+            total: state.total,
+            head: Some(new_head.id().to_string()),
+            skipped: state.skipped,
+        })
+    }
+
+    /// Apply one todo item on top of the current HEAD
+    fn apply_rebase_item(&self, item: &RebaseTodoItem, skipped: &mut Vec<String>) -> Result<StepOutcome> {
+        let action = RebaseAction::parse(&item.action)?;
+        if action == RebaseAction::Drop {
+            return Ok(StepOutcome::Applied);
+        }
+
+        let commit = self.repo.find_commit(Oid::from_str(&item.commit)?)?;
+        let head = self.repo.head()?.peel_to_commit()?;
+
+        // Unchanged commits whose parent is already HEAD are reused as-is
+        let reusable = matches!(action, RebaseAction::Pick | RebaseAction::Edit)
+            || (action == RebaseAction::Reword && item.message.as_deref() == commit.message());
+        if reusable && commit.parent_id(0).ok() == Some(head.id()) {
+            self.checkout_detached(&commit, None)?;
+            return Ok(if action == RebaseAction::Edit {
+                StepOutcome::StoppedForEdit
+            } else {
+                StepOutcome::Applied
+            });
+        }
+
+        self.repo.cherrypick(&commit, None)
+            .context(format!("Failed to apply commit {}", &item.commit[..7]))?;
+
+        if self.repo.index()?.has_conflicts() {
+            return Ok(StepOutcome::Conflicts);
+        }
+
+        if !self.commit_rebase_item(item)? {
+            skipped.push(item.commit.clone());
+        }
+
+        Ok(if action == RebaseAction::Edit {
+            StepOutcome::StoppedForEdit
+        } else {
+            StepOutcome::Applied
+        })
+    }
+
+    /// Commit the index for an applied todo item
+    ///
+    /// Returns `false` when the item was skipped because its changes are already upstream.
+    fn commit_rebase_item(&self, item: &RebaseTodoItem) -> Result<bool> {
+        let action = RebaseAction::parse(&item.action)?;
+        let original = self.repo.find_commit(Oid::from_str(&item.commit)?)?;
+        let head = self.repo.head()?.peel_to_commit()?;
+
+        let mut index = self.repo.index()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let committer = self.repo.signature()?;
+
+        match action {
+            RebaseAction::Squash | RebaseAction::Fixup => {
+                let message = match action {
+                    RebaseAction::Squash => item.message.clone().unwrap_or_else(|| {
+                        let squashed = original.message().unwrap_or("");
+                        // Drop the "squash! <subject>" line created by commit_fixup
+                        let squashed = if squashed.starts_with("squash! ") {
+                            squashed.split_once('\n').map(|(_, rest)| rest.trim_start()).unwrap_or("")
+                        } else {
+                            squashed
+                        };
+                        let head_message = head.message().unwrap_or("").trim_end();
+                        if squashed.trim().is_empty() {
+                            head_message.to_string()
+                        } else {
+                            format!("{}\n\n{}", head_message, squashed)
+                        }
+                    }),
+                    _ => head.message().unwrap_or("").to_string(),
+                };
+                let parents: Vec<git2::Commit> = head.parents().collect();
+                let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+                let oid = self.create_commit(
+                    None,
+                    &head.author(),
+                    &committer,
+                    &message,
+                    &tree,
+                    &parent_refs,
+                )?;
+                self.repo.set_head_detached(oid)?;
+            }
+            _ => {
+                // A non-empty commit whose changes are already upstream leaves nothing
+                // to commit; a commit that was empty to begin with is kept
+                let originally_empty = match original.parent(0) {
+                    Ok(parent) => parent.tree_id() == original.tree_id(),
+                    Err(_) => original.tree()?.is_empty(),
+                };
+                if tree.id() == head.tree_id() && !originally_empty {
+                    self.repo.cleanup_state()?;
+                    return Ok(false);
+                }
+
+                let message = item.message.as_deref()
+                    .or(original.message())
+                    .unwrap_or("");
+                self.create_commit(
+                    Some("HEAD"),
+                    &original.author(),
+                    &committer,
+                    message,
+                    &tree,
+                    &[&head],
+                )?;
+            }
+        }
+
+        self.repo.cleanup_state()?;
+        Ok(true)
+    }
+
+    /// Fold staged changes into HEAD, keeping its message and author
+    fn amend_head_with_index(&self) -> Result<()> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let mut index = self.repo.index()?;
+        let tree_id = index.write_tree()?;
+        if tree_id == head.tree_id() {
+            return Ok(());
+        }
+
+        let tree = self.repo.find_tree(tree_id)?;
+        let parents: Vec<git2::Commit> = head.parents().collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        let oid = self.create_commit(
+            None,
+            &head.author(),
+            &self.repo.signature()?,
+            head.message().unwrap_or(""),
+            &tree,
+            &parent_refs,
+        )?;
+        self.repo.set_head_detached(oid)?;
+        Ok(())
+    }
+
+    fn validate_rebase_plan(items: &[RebaseTodoItem]) -> Result<()> {
+        let mut has_base = false;
+        for item in items {
+            match RebaseAction::parse(&item.action)? {
+                RebaseAction::Squash | RebaseAction::Fixup if !has_base => {
+                    anyhow::bail!("Cannot '{}' without a previous commit: {}", item.action, item.commit);
+                }
+                RebaseAction::Reword if item.message.as_deref().unwrap_or("").trim().is_empty() => {
+                    anyhow::bail!("A new message is required to reword {}", item.commit);
+                }
+                RebaseAction::Drop => {}
+                _ => has_base = true,
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a rebase started here is waiting to be continued, skipped or aborted
+    ///
+    /// The sequencer keeps its own state, so `repo.state()` stays clean meanwhile.
+    pub(crate) fn rebase_in_progress(&self) -> bool {
+        self.rebase_state_path().exists()
+    }
+
+    /// Refuse to run an operation that would move HEAD or the index under an in-progress rebase
+    pub(crate) fn ensure_no_rebase_in_progress(&self, operation: &str) -> Result<()> {
+        if self.rebase_in_progress() {
+            anyhow::bail!(
+                "Cannot {}: a rebase is in progress. Continue, skip or abort it first.",
+                operation
+            );
+        }
+        Ok(())
+    }
+
+    /// Refuse to commit during a rebase, except while it is stopped to edit a commit
+    pub(crate) fn ensure_rebase_allows_commit(&self) -> Result<()> {
+        if self.rebase_in_progress() && !self.load_rebase_state()?.stopped_for_edit {
+            anyhow::bail!("Cannot commit: a rebase is in progress. Continue, skip or abort it first.");
+        }
+        Ok(())
+    }
+
+    /// Refuse to start a history-rewriting operation with uncommitted changes
+    pub(crate) fn ensure_clean_worktree(&self, operation: &str) -> Result<()> {
+        self.ensure_no_rebase_in_progress(operation)?;
+        if self.has_uncommitted_changes(STAGED_CHANGES | UNSTAGED_CHANGES)? {
+            anyhow::bail!(
+                "Cannot {}: You have uncommitted changes. \
+                Please commit or stash your changes first.",
+                operation
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether any tracked file has one of the `changes` statuses
+    ///
+    /// Files left out by sparse checkout look deleted to libgit2 and are not counted.
+    fn has_uncommitted_changes(&self, changes: Status) -> Result<bool> {
+        let statuses = self.repo.statuses(None)?;
+        Ok(statuses.iter().any(|entry| {
+            let status = entry.status() & changes;
+            if status == Status::WT_DELETED {
+                return !entry.path().is_some_and(|path| self.is_sparse_excluded(path));
+            }
+            !status.is_empty()
+        }))
+    }
+
+    fn rebase_state_path(&self) -> PathBuf {
+        self.repo.path().join(REBASE_STATE_FILE)
+    }
+
+    fn load_rebase_state(&self) -> Result<RebaseState> {
+        let path = self.rebase_state_path();
+        if !path.exists() {
+            anyhow::bail!("No rebase in progress");
+        }
+        let content = std::fs::read_to_string(&path)
+            .context("Failed to read rebase state")?;
+        serde_json::from_str(&content).context("Rebase state is corrupted")
+    }
+
+    fn save_rebase_state(&self, state: &RebaseState) -> Result<()> {
+        let content = serde_json::to_string_pretty(state)?;
+        std::fs::write(self.rebase_state_path(), content)
+            .context("Failed to write rebase state")
+    }
+
+    fn rebase_status_from(&self, state: &RebaseState, label: &str) -> RebaseStatus {
+        let pending = state.todo.len() + usize::from(state.current.is_some() && !state.stopped_for_edit);
+        RebaseStatus {
+            state: label.to_string(),
+            current: state.current.clone(),
+            done: state.total.saturating_sub(pending),
+            total: state.total,
+            head: self.repo.head().ok()
+                .and_then(|h| h.target())
+                .map(|oid| oid.to_string()),
+            skipped: state.skipped.clone(),
+        }
+    }
+}
+
+/// Split a `fixup! ...`/`squash! ...` summary into its action and target subject
+fn parse_autosquash_summary(summary: &str) -> Option<(&'static str, &str)> {
+    let mut action = None;
+    let mut subject = summary;
+    loop {
+        if let Some(rest) = subject.strip_prefix("fixup! ") {
+            action.get_or_insert("fixup");
+            subject = rest;
+        } else if let Some(rest) = subject.strip_prefix("squash! ") {
+            action.get_or_insert("squash");
+            subject = rest;
+        } else {
+            break;
+        }
+    }
+    action.map(|action| (action, subject.trim()))
+}
+
+/// Move `fixup!`/`squash!` commits right after the commit they target, like `git rebase --autosquash`
+fn autosquash_plan(plan: Vec<RebaseTodoItem>) -> Vec<RebaseTodoItem> {
+    let mut groups: Vec<(RebaseTodoItem, Vec<RebaseTodoItem>)> = Vec::new();
+
+    for mut item in plan {
+        if let Some((action, subject)) = parse_autosquash_summary(&item.summary) {
+            let target = groups.iter_mut().find(|(head, _)| {
+                head.summary == subject || (subject.len() >= 7 && head.commit.starts_with(subject))
+            });
+            if let Some((_, followers)) = target {
+                item.action = action.to_string();
+                followers.push(item);
+                continue;
+            }
+        }
+        groups.push((item, Vec::new()));
+    }
+
+    groups
+        .into_iter()
+        .flat_map(|(head, followers)| std::iter::once(head).chain(followers))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+    use std::fs;
+
+    fn commit_empty(repo: &GitRepository, message: &str) -> String {
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = repo.repo.signature().unwrap();
+        repo.repo.commit(Some("HEAD"), &sig, &sig, message, &head.tree().unwrap(), &[&head])
+            .unwrap()
+            .to_string()
+    }
+
+    /// Create branch `upstream` at HEAD with one commit, then return to the original branch
+    fn diverge(repo: &GitRepository, name: &str, content: &str) {
+        let branch = repo.repo.head().unwrap().name().unwrap().to_string();
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        repo.repo.branch("upstream", &head, false).unwrap();
+        repo.repo.set_head("refs/heads/upstream").unwrap();
+        commit_file(repo, name, content, "upstream change");
+        repo.repo.set_head(&branch).unwrap();
+        repo.repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+    }
+
+    fn head_summaries(repo: &GitRepository, count: usize) -> Vec<String> {
+        let mut commit = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        let mut summaries = vec![commit.summary().unwrap().to_string()];
+        while summaries.len() < count {
+            commit = commit.parent(0).unwrap();
+            summaries.push(commit.summary().unwrap().to_string());
+        }
+        summaries
+    }
+
+    #[test]
+    fn test_rebase_keeps_empty_commits() {
+        let (_dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "up.txt", "up\n");
+        commit_file(&repo, "a.txt", "a\n", "add a");
+        commit_empty(&repo, "empty");
+
+        let plan = repo.get_rebase_plan("upstream").unwrap();
+        let status = repo.rebase_interactive("upstream", plan).unwrap();
+        assert_eq!(status.state, "completed");
+        assert!(status.skipped.is_empty());
+        assert_eq!(head_summaries(&repo, 3), vec!["empty", "add a", "upstream change"]);
+    }
+
+    #[test]
+    fn test_rebase_reports_picks_already_upstream() {
+        let (_dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "a.txt", "a\n");
+        let duplicate = commit_file(&repo, "a.txt", "a\n", "add a again");
+        commit_file(&repo, "b.txt", "b\n", "add b");
+
+        let plan = repo.get_rebase_plan("upstream").unwrap();
+        let status = repo.rebase_interactive("upstream", plan).unwrap();
+        assert_eq!(status.state, "completed");
+        assert_eq!(status.skipped, vec![duplicate]);
+        assert_eq!(head_summaries(&repo, 2), vec!["add b", "upstream change"]);
+    }
+
+    #[test]
+    fn test_rebase_failed_step_is_retried_on_continue() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "up.txt", "up\n");
+        commit_file(&repo, "a.txt", "a\n", "add a");
+        let add_b = commit_file(&repo, "b.txt", "b\n", "add b");
+
+        let mut plan = repo.get_rebase_plan("upstream").unwrap();
+        plan[0].action = "edit".to_string();
+        let status = repo.rebase_interactive("upstream", plan).unwrap();
+        assert_eq!(status.state, "stopped");
+
+        // An untracked b.txt makes the next pick fail without conflicts
+        fs::write(dir.path().join("b.txt"), "untracked\n").unwrap();
+        assert!(repo.rebase_continue().is_err());
+        let status = repo.get_rebase_status().unwrap();
+        assert_eq!(status.state, "failed");
+        assert!(status.current.is_none());
+        assert_eq!(head_summaries(&repo, 1), vec!["add a"]);
+
+        fs::remove_file(dir.path().join("b.txt")).unwrap();
+        let status = repo.rebase_continue().unwrap();
+        assert_eq!(status.state, "completed");
+        assert_eq!(head_summaries(&repo, 3), vec!["add b", "add a", "upstream change"]);
+        assert_eq!(fs::read_to_string(dir.path().join("b.txt")).unwrap(), "b\n");
+        assert_ne!(repo.repo.head().unwrap().target().unwrap().to_string(), add_b);
+    }
+
+    #[test]
+    fn test_rebase_continue_keeps_unstaged_edits() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "up.txt", "up\n");
+        commit_file(&repo, "a.txt", "a\n", "add a");
+        commit_file(&repo, "b.txt", "b\n", "add b");
+
+        let mut plan = repo.get_rebase_plan("upstream").unwrap();
+        plan[0].action = "edit".to_string();
+        assert_eq!(repo.rebase_interactive("upstream", plan).unwrap().state, "stopped");
+
+        fs::write(dir.path().join("a.txt"), "edited\n").unwrap();
+        assert!(repo.rebase_continue().is_err());
+        assert!(repo.rebase_skip().is_err());
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "edited\n");
+        assert_eq!(repo.get_rebase_status().unwrap().state, "stopped");
+
+        repo.stage_file("a.txt").unwrap();
+        assert_eq!(repo.rebase_continue().unwrap().state, "completed");
+        assert_eq!(head_summaries(&repo, 2), vec!["add b", "add a"]);
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "edited\n");
+    }
+
+    #[test]
+    fn test_other_operations_wait_for_rebase() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "a.txt", "upstream\n");
+        commit_file(&repo, "a.txt", "mine\n", "add a");
+
+        let plan = repo.get_rebase_plan("upstream").unwrap();
+        assert_eq!(repo.rebase_interactive("upstream", plan).unwrap().state, "conflicts");
+        assert_eq!(repo.repo.state(), git2::RepositoryState::CherryPick);
+        repo.repo.cleanup_state().unwrap();
+
+        fs::write(dir.path().join("a.txt"), "resolved\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        assert!(repo.commit("sneaky").is_err());
+        assert!(repo.checkout_branch("upstream").is_err());
+        assert!(repo.reset("HEAD", "soft").is_err());
+        assert!(repo.merge("upstream").is_err());
+        assert!(repo.bisect_start("HEAD", &["upstream".to_string()]).is_err());
+
+        repo.rebase_abort().unwrap();
+        assert!(!repo.rebase_in_progress());
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "mine\n");
+        repo.checkout_branch("upstream").unwrap();
+    }
+
+    #[test]
+    fn test_commit_allowed_while_stopped_for_edit() {
+        let (_dir, repo) = init_repo();
+        commit_file(&repo, "base.txt", "base\n", "base");
+        diverge(&repo, "up.txt", "up\n");
+        commit_file(&repo, "a.txt", "a\n", "add a");
+
+        let mut plan = repo.get_rebase_plan("upstream").unwrap();
+        plan[0].action = "edit".to_string();
+        assert_eq!(repo.rebase_interactive("upstream", plan).unwrap().state, "stopped");
+
+        commit_file(&repo, "extra.txt", "extra\n", "add extra");
+        assert_eq!(repo.rebase_continue().unwrap().state, "completed");
+        assert_eq!(head_summaries(&repo, 3), vec!["add extra", "add a", "upstream change"]);
+    }
+
+    fn pick(commit: &str, summary: &str) -> RebaseTodoItem {
+        RebaseTodoItem {
+            action: "pick".to_string(),
+            commit: commit.to_string(),
+            summary: summary.to_string(),
+            message: None,
+        }
+    }
+
+    #[test]
+    fn test_autosquash_plan_moves_fixups_after_target() {
+        let plan = vec![
+            pick("aaaaaaa1", "Add parser"),
+            pick("bbbbbbb2", "Add lexer"),
+            pick("ccccccc3", "fixup! Add parser"),
+            pick("ddddddd4", "squash! fixup! Add parser"),
+            pick("eeeeeee5", "fixup! bbbbbbb2"),
+            pick("fffffff6", "fixup! Unknown commit"),
+        ];
+
+        let result = autosquash_plan(plan);
+        let order: Vec<(&str, &str)> = result.iter()
+            .map(|item| (item.commit.as_str(), item.action.as_str()))
+            .collect();
+
+        assert_eq!(order, vec![
+            ("aaaaaaa1", "pick"),
+            ("ccccccc3", "fixup"),
+            ("ddddddd4", "squash"),
+            ("bbbbbbb2", "pick"),
+            ("eeeeeee5", "fixup"),
+            ("fffffff6", "pick"),
+        ]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Lost commit recovery
+//!
+//! This module finds commits that are no longer reachable from any reference,
+//! either through reflog entries or as dangling objects (for example dropped
+//! stashes), and re-creates branches pointing at them.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use git2::{ObjectType, Oid};
+
+use super::repository::GitRepository;
+use super::types::LostCommit;
+
+impl GitRepository {
+    /// Find unreachable commits, newest first
+    ///
+    /// Only the tip of each lost line of history is returned; its ancestors
+    /// come back with it once a branch is created.
+    pub fn find_lost_commits(&self, max_count: Option<usize>) -> Result<Vec<LostCommit>> {
+        let reflog_sources = self.reflog_commit_sources()?;
+
+        let mut candidates: HashSet<Oid> = reflog_sources.keys().copied().collect();
+        let odb = self.repo.odb()?;
+        odb.foreach(|oid| {
+            if matches!(odb.read_header(*oid), Ok((_, ObjectType::Commit))) {
+                candidates.insert(*oid);
+            }
+            true
+        })?;
+
+        // Everything reachable from the candidates but not from a reference
+        let mut revwalk = self.repo.revwalk()?;
+        for oid in &candidates {
+            if self.repo.find_commit(*oid).is_ok() {
+                revwalk.push(*oid)?;
+            }
+        }
+        revwalk.hide_glob("*")?;
+        if let Ok(head) = self.repo.head() {
+            if let Some(oid) = head.target() {
+                revwalk.hide(oid)?;
+            }
+        }
+        // Older stash entries are only referenced by the stash reflog but are not lost
+        if let Ok(stash_log) = self.repo.reflog("refs/stash") {
+            for entry in stash_log.iter() {
+                if self.repo.find_commit(entry.id_new()).is_ok() {
+                    revwalk.hide(entry.id_new())?;
+                }
+            }
+        }
+
+        let mut unreachable = Vec::new();
+        let mut has_lost_child = HashSet::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            has_lost_child.extend(commit.parent_ids());
+            unreachable.push(commit);
+        }
+
+        // Limit before computing file changes, which means diffing every tip
+        let mut tips: Vec<&git2::Commit> = unreachable.iter()
+            .filter(|c| !has_lost_child.contains(&c.id()))
+            .collect();
+        tips.sort_by_key(|c| std::cmp::Reverse(c.time().seconds()));
+        tips.truncate(max_count.unwrap_or(100));
+
+        let mut lost = Vec::new();
+        for commit in tips {
+            let source = match reflog_sources.get(&commit.id()) {
+                Some(selector) => selector.clone(),
+                None if is_stash_commit(commit) => "stash".to_string(),
+                None => "dangling".to_string(),
+            };
+            let files = self.commit_file_changes(commit)?;
+            lost.push(LostCommit {
+                commit: self.commit_info(commit),
+                source,
+                insertions: files.iter().map(|f| f.insertions).sum(),
+                deletions: files.iter().map(|f| f.deletions).sum(),
+                files,
+            });
+        }
+
+        Ok(lost)
+    }
+
+    /// Create a branch at a lost commit
+    pub fn recover_branch(&self, commit_hash: &str, branch_name: &str) -> Result<()> {
+        let oid = Oid::from_str(commit_hash)
+            .context(format!("Invalid commit id: {}", commit_hash))?;
+        let commit = self.repo.find_commit(oid)
+            .context(format!("引用 {} 不是有效的提交", commit_hash))?;
+        self.repo.branch(branch_name, &commit, false)
+            .context(format!("Failed to create branch '{}'", branch_name))?;
+        Ok(())
+    }
+
+    /// Map each commit mentioned in a reflog to its newest selector, e.g. `HEAD@{2}`
+    fn reflog_commit_sources(&self) -> Result<HashMap<Oid, String>> {
+        let mut names = vec!["HEAD".to_string()];
+        for reference in self.repo.references()? {
+            if let Some(name) = reference?.name() {
+                names.push(name.to_string());
+            }
+        }
+
+        let mut sources = HashMap::new();
+        // The stash reflog lists live stash entries, not lost commits
+        for name in names.into_iter().filter(|name| name != "refs/stash") {
+            let Ok(reflog) = self.repo.reflog(&name) else { continue };
+            let short = name.strip_prefix("refs/heads/").unwrap_or(&name);
+            for (index, entry) in reflog.iter().enumerate() {
+                for oid in [entry.id_new(), entry.id_old()] {
+                    if !oid.is_zero() {
+                        sources.entry(oid).or_insert_with(|| format!("{}@{{{}}}", short, index));
+                    }
+                }
+            }
+        }
+        Ok(sources)
+    }
+}
+
+/// Whether a commit looks like one created by `git stash`
+fn is_stash_commit(commit: &git2::Commit) -> bool {
+    let summary = commit.summary().unwrap_or("");
+    commit.parent_count() >= 2 && (summary.starts_with("WIP on ") || summary.starts_with("On "))
+}
@@ -0,0 +1,62 @@
+//! Reflog reading
+//!
+//! This module lists the references that keep a reflog and reads their entries.
+
+use anyhow::{Context, Result};
+
+use super::repository::GitRepository;
+use super::types::ReflogEntry;
+
+impl GitRepository {
+    /// References with a reflog: HEAD followed by every local branch
+    pub fn get_reflog_refs(&self) -> Result<Vec<String>> {
+        let mut names = vec!["HEAD".to_string()];
+        for reference in self.repo.references_glob("refs/heads/*")? {
+            let reference = reference?;
+            if let Some(name) = reference.name() {
+                if self.repo.reflog(name).map(|log| !log.is_empty()).unwrap_or(false) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Read the reflog of `HEAD`, a branch name or a full reference name, newest first
+    pub fn get_reflog(&self, reference: &str, max_count: Option<usize>) -> Result<Vec<ReflogEntry>> {
+        let name = self.resolve_reflog_name(reference);
+        let reflog = self.repo.reflog(&name)
+            .context(format!("Failed to read reflog of {}", name))?;
+
+        let limit = max_count.unwrap_or(usize::MAX);
+        Ok(reflog.iter()
+            .take(limit)
+            .enumerate()
+            .map(|(index, entry)| {
+                let committer = entry.committer();
+                ReflogEntry {
+                    index,
+                    old_id: entry.id_old().to_string(),
+                    new_id: entry.id_new().to_string(),
+                    committer: committer.name().unwrap_or("").to_string(),
+                    email: committer.email().unwrap_or("").to_string(),
+                    timestamp: committer.when().seconds(),
+                    message: entry.message().unwrap_or("").to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// Expand a short branch name to its full reference name
+    fn resolve_reflog_name(&self, reference: &str) -> String {
+        if reference == "HEAD" || reference.starts_with("refs/") {
+            return reference.to_string();
+        }
+        let branch = format!("refs/heads/{}", reference);
+        if self.repo.find_reference(&branch).is_ok() || self.repo.reflog(&branch).is_ok_and(|log| !log.is_empty()) {
+            branch
+        } else {
+            reference.to_string()
+        }
+    }
+}
@@ -7,11 +7,13 @@ use std::path::Path;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 
+use super::hooks::HookOptions;
 use super::types::{FileChange, CommitInfo, SyncStatus};
 
 /// Main struct for Git repository operations
 pub struct GitRepository {
     pub(crate) repo: Repository,
+    pub(crate) hooks: HookOptions,
 }
 
 impl GitRepository {
@@ -19,21 +21,7 @@ impl GitRepository {
     pub fn open(path: &str) -> Result<Self> {
         let repo = Repository::open(path)
             .context(format!("Failed to open repository at {}", path))?;
-        Ok(Self { repo })
-    }
-
-    /// Initialize a new repository
-    pub fn init(path: &str) -> Result<Self> {
-        let repo = Repository::init(path)
-            .context(format!("Failed to initialize repository at {}", path))?;
-        Ok(Self { repo })
-    }
-
-    /// Clone a repository from a URL
-    pub fn clone(url: &str, path: &str) -> Result<Self> {
-        let repo = Repository::clone(url, path)
-            .context(format!("Failed to clone repository from {}", url))?;
-        Ok(Self { repo })
+        Ok(Self { repo, hooks: HookOptions::default() })
     }
 
     /// Get the status of all files in the repository
@@ -44,12 +32,23 @@ impl GitRepository {
             .recurse_untracked_dirs(true);
 
         let statuses = self.repo.statuses(Some(&mut status_opts))?;
+        let submodule_paths = self.submodule_paths();
         let mut changes = Vec::new();
 
         for entry in statuses.iter() {
             let path = entry.path().unwrap_or("").to_string();
             let status_flags = entry.status();
 
+            // Submodules get their own status carrying the pointer change
+            if submodule_paths.contains(&path) {
+                let staged = status_flags.intersects(
+                    Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED,
+                );
+                let submodule = self.submodule_change(&path).ok();
+                changes.push(FileChange { path, status: "submodule".to_string(), staged, submodule });
+                continue;
+            }
+
             let (status, staged) = if status_flags.contains(Status::INDEX_NEW) {
                 ("added".to_string(), true)
             } else if status_flags.contains(Status::INDEX_MODIFIED) {
@@ -59,8 +58,16 @@ impl GitRepository {
             } else if status_flags.contains(Status::INDEX_RENAMED) {
                 ("renamed".to_string(), true)
             } else if status_flags.contains(Status::WT_MODIFIED) {
+                // Checked-out LFS content differs from the committed pointer but is not a change
+                if self.is_lfs_path(&path) && self.is_smudged_lfs_file(&path) {
+                    continue;
+                }
                 ("modified".to_string(), false)
             } else if status_flags.contains(Status::WT_DELETED) {
+                // Files outside the sparse-checkout cone are absent on purpose
+                if self.is_sparse_excluded(&path) {
+                    continue;
+                }
                 ("deleted".to_string(), false)
             } else if status_flags.contains(Status::WT_NEW) {
                 ("untracked".to_string(), false)
@@ -68,7 +75,7 @@ impl GitRepository {
                 continue;
             };
 
-            changes.push(FileChange { path, status, staged });
+            changes.push(FileChange { path, status, staged, submodule: None });
         }
 
         Ok(changes)
@@ -183,10 +190,15 @@ impl GitRepository {
         Ok(())
     }
 
-    /// Create a new commit
+    /// Create a new commit, running the commit hooks
     pub fn commit(&self, message: &str) -> Result<String> {
+        self.ensure_rebase_allows_commit()?;
+        let message = self.run_commit_hooks(message, "message", None)?;
+
         let signature = self.repo.signature()?;
         let mut index = self.repo.index()?;
+        // Hooks such as lint-staged may have re-staged files
+        index.read(false)?;
         let tree_id = index.write_tree()?;
         let tree = self.repo.find_tree(tree_id)?;
 
@@ -200,15 +212,51 @@ impl GitRepository {
             None => vec![],
         };
 
-        let oid = self.repo.commit(
+        let oid = self.create_commit(
             Some("HEAD"),
             &signature,
             &signature,
-            message,
+            &message,
             &tree,
             &parents,
         )?;
 
+        self.run_post_hook("post-commit", &[]);
+        Ok(oid.to_string())
+    }
+
+    /// Amend HEAD with the current index and an optional new message, running the commit hooks
+    pub fn commit_amend(&self, message: Option<&str>) -> Result<String> {
+        self.ensure_rebase_allows_commit()?;
+        let head_commit = self.repo.head()
+            .context("Nothing to amend: repository has no commits")?
+            .peel_to_commit()?;
+
+        let message = message
+            .filter(|m| !m.trim().is_empty())
+            .or(head_commit.message())
+            .unwrap_or("");
+        let head_id = head_commit.id().to_string();
+        let message = self.run_commit_hooks(message, "commit", Some(&head_id))?;
+
+        let signature = self.repo.signature()?;
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        let tree_id = index.write_tree()?;
+        let tree = self.repo.find_tree(tree_id)?;
+        let parents: Vec<git2::Commit> = head_commit.parents().collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+        let oid = self.create_commit(
+            Some("HEAD"),
+            &head_commit.author(),
+            &signature,
+            &message,
+            &tree,
+            &parent_refs,
+        )?;
+
+        self.run_post_hook("post-commit", &[]);
         Ok(oid.to_string())
     }
 
@@ -226,27 +274,32 @@ impl GitRepository {
                 break;
             }
 
-            let oid = oid?;
-            let commit = self.repo.find_commit(oid)?;
-
-            let timestamp = commit.time().seconds();
-            let date = DateTime::<Utc>::from_timestamp(timestamp, 0)
-                .map(|dt| dt.to_rfc3339())
-                .unwrap_or_else(|| format!("Invalid timestamp: {}", timestamp));
-
-            commits.push(CommitInfo {
-                hash: oid.to_string(),
-                message: commit.message().unwrap_or("").to_string(),
-                author: commit.author().name().unwrap_or("").to_string(),
-                email: commit.author().email().unwrap_or("").to_string(),
-                date,
-                parents: commit.parents().map(|p| p.id().to_string()).collect(),
-            });
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(self.commit_info(&commit));
         }
 
         Ok(commits)
     }
 
+    /// Build the `CommitInfo` summary of a commit
+    pub(crate) fn commit_info(&self, commit: &git2::Commit) -> CommitInfo {
+        let timestamp = commit.time().seconds();
+        let date = DateTime::<Utc>::from_timestamp(timestamp, 0)
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_else(|| format!("Invalid timestamp: {}", timestamp));
+
+        CommitInfo {
+            hash: commit.id().to_string(),
+            message: commit.message().unwrap_or("").to_string(),
+            author: commit.author().name().unwrap_or("").to_string(),
+            email: commit.author().email().unwrap_or("").to_string(),
+            date,
+            parents: commit.parents().map(|p| p.id().to_string()).collect(),
+            signed: commit.header_field_bytes("gpgsig").is_ok()
+                || commit.header_field_bytes("gpgsig-sha256").is_ok(),
+        }
+    }
+
     /// Get commits between two refs
     pub fn get_commits_between(&self, from_ref: &str, to_ref: &str) -> Result<Vec<CommitInfo>> {
         let mut revwalk = self.repo.revwalk()?;
@@ -266,22 +319,8 @@ impl GitRepository {
 
         let mut commits = Vec::new();
         for oid in revwalk {
-            let oid = oid?;
-            let commit = self.repo.find_commit(oid)?;
-
-            let timestamp = commit.time().seconds();
-            let date = DateTime::<Utc>::from_timestamp(timestamp, 0)
-                .map(|dt| dt.to_rfc3339())
-                .unwrap_or_else(|| format!("Invalid timestamp: {}", timestamp));
-
-            commits.push(CommitInfo {
-                hash: oid.to_string(),
-                message: commit.message().unwrap_or("").to_string(),
-                author: commit.author().name().unwrap_or("").to_string(),
-                email: commit.author().email().unwrap_or("").to_string(),
-                date,
-                parents: commit.parents().map(|p| p.id().to_string()).collect(),
-            });
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(self.commit_info(&commit));
         }
 
         Ok(commits)
@@ -0,0 +1,237 @@
+//! Reset operations
+//!
+//! This module moves the current branch to another commit in soft, mixed or
+//! hard mode and resets individual paths in the index. A hard reset first
+//! saves the changes it would discard under a private ref so it can be undone.
+
+use std::collections::HashSet;
+
+use git2::{Oid, ResetType, Status, StatusOptions};
+use anyhow::{Context, Result};
+
+use super::repository::GitRepository;
+use super::types::ResetResult;
+
+/// Namespace of the commits saved before a hard reset, kept out of branches and the stash
+pub(super) const RESET_SNAPSHOT_PREFIX: &str = "refs/caogit/reset-snapshots/";
+
+/// Older snapshots are dropped beyond this many
+const MAX_RESET_SNAPSHOTS: usize = 20;
+
+impl GitRepository {
+    /// Move the current branch to `target` (`"soft"`, `"mixed"` or `"hard"`)
+    pub fn reset(&self, target: &str, mode: &str) -> Result<ResetResult> {
+        let reset_type = match mode {
+            "soft" => ResetType::Soft,
+            "mixed" => ResetType::Mixed,
+            "hard" => ResetType::Hard,
+            other => anyhow::bail!("Unknown reset mode: {}", other),
+        };
+        self.ensure_no_rebase_in_progress("reset")?;
+
+        // Resolve before snapshotting so revspecs like `stash@{0}` keep their meaning
+        let target_id = self.repo.revparse_single(target)
+            .context(format!("无法找到引用: {}", target))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", target))?
+            .id();
+        let previous_head = self.repo.head()
+            .context("Cannot reset: repository has no commits")?
+            .peel_to_commit()?
+            .id();
+
+        let snapshot = if reset_type == ResetType::Hard {
+            self.snapshot_before_reset(target)?
+        } else {
+            None
+        };
+
+        let target_commit = self.repo.find_commit(target_id)?;
+        self.repo.reference("ORIG_HEAD", previous_head, true, "updating ORIG_HEAD")?;
+        self.reset_head(&target_commit, reset_type)
+            .context(format!("Failed to reset to {}", target))?;
+
+        Ok(ResetResult {
+            previous_head: previous_head.to_string(),
+            new_head: target_id.to_string(),
+            snapshot: snapshot.map(|oid| oid.to_string()),
+        })
+    }
+
+    /// Undo a hard reset: move back to `previous_head` and restore the saved snapshot
+    pub fn undo_hard_reset(&self, previous_head: &str, snapshot: Option<&str>) -> Result<()> {
+        self.ensure_clean_worktree("undo the reset")?;
+
+        {
+            let commit = self.repo.revparse_single(previous_head)
+                .context(format!("无法找到引用: {}", previous_head))?
+                .peel_to_commit()?;
+            self.reset_head(&commit, ResetType::Hard)?;
+        }
+
+        if let Some(snapshot) = snapshot {
+            let oid = Oid::from_str(snapshot)
+                .context(format!("Invalid snapshot id: {}", snapshot))?;
+            self.restore_reset_snapshot(oid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset paths in the index to their state in `commit`, like `git reset <commit> -- <paths>`
+    pub fn reset_paths(&self, commit: &str, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            anyhow::bail!("No paths given to reset");
+        }
+
+        let object = self.repo.revparse_single(commit)
+            .context(format!("无法找到引用: {}", commit))?
+            .peel_to_commit()
+            .context(format!("引用 {} 不是有效的提交", commit))?
+            .into_object();
+        self.repo.reset_default(Some(&object), paths.iter())
+            .context("Failed to reset paths")?;
+        Ok(())
+    }
+
+    /// Commits currently saved under `RESET_SNAPSHOT_PREFIX`
+    pub(super) fn reset_snapshots(&self) -> Result<HashSet<Oid>> {
+        let mut snapshots = HashSet::new();
+        for reference in self.repo.references_glob(&format!("{}*", RESET_SNAPSHOT_PREFIX))? {
+            if let Some(oid) = reference?.target() {
+                snapshots.insert(oid);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Restore the working directory and index saved by `snapshot_before_reset` and drop its ref
+    pub(super) fn restore_reset_snapshot(&self, oid: Oid) -> Result<()> {
+        let snapshot = self.repo.find_commit(oid)
+            .context(format!("Reset snapshot {} no longer exists", oid))?;
+        let index_tree = snapshot.parent(1)
+            .context(format!("{} is not a reset snapshot", oid))?
+            .tree()?;
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force();
+        self.repo.checkout_tree(snapshot.as_object(), Some(&mut checkout))
+            .context("Failed to restore the reset snapshot")?;
+        let mut index = self.repo.index()?;
+        index.read_tree(&index_tree)?;
+        index.write()?;
+        if self.is_sparse_checkout() {
+            // The restored index lost its skip-worktree bits and the checkout wrote every file
+            self.reapply_sparse_checkout()?;
+        }
+
+        for reference in self.repo.references_glob(&format!("{}*", RESET_SNAPSHOT_PREFIX))? {
+            let mut reference = reference?;
+            if reference.target() == Some(oid) {
+                reference.delete()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save tracked changes before a hard reset discards them
+    ///
+    /// The snapshot is laid out like a stash commit (working tree with HEAD and index
+    /// commits as parents) but lives under `RESET_SNAPSHOT_PREFIX` so the stash list is
+    /// left alone. Untracked files are skipped since a hard reset does not touch them either.
+    fn snapshot_before_reset(&self, target: &str) -> Result<Option<Oid>> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let mut index = self.repo.index()?;
+        if index.has_conflicts() {
+            anyhow::bail!("Cannot snapshot changes before reset: resolve the conflicts first");
+        }
+        let index_tree_id = index.write_tree()?;
+
+        // Stage tracked working directory changes in memory only; the index is re-read below
+        let mut status_opts = StatusOptions::new();
+        status_opts.include_untracked(false).include_ignored(false);
+        let statuses = self.repo.statuses(Some(&mut status_opts))?;
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            let status = entry.status();
+            // Files outside the sparse-checkout cone are missing on purpose
+            if status.contains(Status::WT_DELETED) && self.is_sparse_excluded(path) {
+                continue;
+            }
+            if status.contains(Status::WT_DELETED) {
+                index.remove_path(std::path::Path::new(path))?;
+            } else if status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE) {
+                index.add_path(std::path::Path::new(path))?;
+            }
+        }
+        let worktree_tree_id = index.write_tree();
+        index.read(true)?;
+        let worktree_tree_id = worktree_tree_id?;
+
+        if index_tree_id == head.tree_id() && worktree_tree_id == head.tree_id() {
+            return Ok(None);
+        }
+
+        let signature = self.repo.signature()?;
+        let index_commit = self.repo.find_commit(self.repo.commit(
+            None, &signature, &signature,
+            &format!("index on caogit reset --hard {}", target),
+            &self.repo.find_tree(index_tree_id)?, &[&head],
+        )?)?;
+        let oid = self.repo.commit(
+            None, &signature, &signature,
+            &format!("caogit: backup before reset --hard {}", target),
+            &self.repo.find_tree(worktree_tree_id)?, &[&head, &index_commit],
+        )?;
+
+        // Named after the snapshot itself, so an identical snapshot just reuses its ref
+        let name = format!("{}{}", RESET_SNAPSHOT_PREFIX, oid);
+        self.repo.reference(&name, oid, true, &format!("reset: snapshot before reset --hard {}", target))
+            .context("Failed to snapshot changes before reset")?;
+        self.prune_reset_snapshots(oid)?;
+        Ok(Some(oid))
+    }
+
+    /// Drop all but the newest `MAX_RESET_SNAPSHOTS` snapshots, always keeping `latest`
+    fn prune_reset_snapshots(&self, latest: Oid) -> Result<()> {
+        let mut snapshots = Vec::new();
+        for reference in self.repo.references_glob(&format!("{}*", RESET_SNAPSHOT_PREFIX))? {
+            let reference = reference?;
+            let time = reference.peel_to_commit().map(|c| c.time().seconds()).unwrap_or(0);
+            // Commit times only have second precision
+            let is_latest = reference.target() == Some(latest);
+            snapshots.push(((is_latest, time), reference));
+        }
+        snapshots.sort_by_key(|(key, _)| std::cmp::Reverse(*key));
+        for (_, mut reference) in snapshots.into_iter().skip(MAX_RESET_SNAPSHOTS) {
+            reference.delete()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+
+    #[test]
+    fn test_hard_reset_snapshots_are_named_by_id_and_pruned() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "f.txt", "one\n", "initial");
+
+        // Back-to-back resets land in the same millisecond and second
+        for i in 0..MAX_RESET_SNAPSHOTS + 2 {
+            std::fs::write(dir.path().join("f.txt"), format!("dirty {}\n", i)).unwrap();
+            let result = repo.reset("HEAD", "hard").unwrap();
+            let snapshot = result.snapshot.unwrap();
+            assert!(repo.repo.find_reference(&format!("{}{}", RESET_SNAPSHOT_PREFIX, snapshot)).is_ok());
+        }
+        assert_eq!(repo.reset_snapshots().unwrap().len(), MAX_RESET_SNAPSHOTS);
+
+        // Nothing to save on a clean tree
+        assert_eq!(repo.reset("HEAD", "hard").unwrap().snapshot, None);
+    }
+}
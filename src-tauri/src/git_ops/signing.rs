@@ -0,0 +1,482 @@
+//! Commit and tag signing
+//!
+//! libgit2 never signs objects by itself. This module reads `commit.gpgsign`,
+//! `tag.gpgsign`, `gpg.format` and `user.signingkey` from git config, signs
+//! commit/tag buffers through a pluggable `Signer` backend (gpg, gpgsm or
+//! ssh-keygen) and verifies existing signatures the same way.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use git2::{Commit, Oid, Signature, Tree};
+use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
+
+use super::repository::GitRepository;
+use super::types::SignatureVerification;
+
+/// A backend able to create and check detached signatures
+pub trait Signer {
+    /// Produce an armored detached signature for `payload`
+    fn sign(&self, payload: &[u8]) -> Result<String>;
+
+    /// Verify an armored detached signature against `payload`
+    fn verify(&self, payload: &[u8], signature: &str) -> Result<SignatureVerification>;
+}
+
+/// Signing through `gpg` (OpenPGP) or `gpgsm` (X.509)
+pub struct GpgSigner {
+    program: String,
+    key: Option<String>,
+    format: &'static str,
+}
+
+/// Signing through `ssh-keygen -Y sign`
+pub struct SshSigner {
+    program: String,
+    key: Option<String>,
+    allowed_signers: Option<PathBuf>,
+}
+
+impl Signer for GpgSigner {
+    fn sign(&self, payload: &[u8]) -> Result<String> {
+        let mut args = vec!["--status-fd=2", "-bsa"];
+        if let Some(key) = &self.key {
+            args.push("-u");
+            args.push(key);
+        }
+
+        let output = run_with_stdin(&self.program, &args, payload)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !stderr.contains("[GNUPG:] SIG_CREATED") {
+            anyhow::bail!("{} failed to sign the data: {}", self.program, stderr.trim());
+        }
+
+        String::from_utf8(output.stdout).context("Signature is not valid UTF-8")
+    }
+
+    fn verify(&self, payload: &[u8], signature: &str) -> Result<SignatureVerification> {
+        let sig_file = temp_file("sig", signature.as_bytes())?;
+        let sig_path = sig_file.path().to_string_lossy().to_string();
+        let args = ["--status-fd=1", "--keyid-format=long", "--verify", sig_path.as_str(), "-"];
+
+        let output = run_with_stdin(&self.program, &args, payload)?;
+        let status_output = String::from_utf8_lossy(&output.stdout).to_string();
+
+        let mut result = SignatureVerification {
+            status: "error".to_string(),
+            format: Some(self.format.to_string()),
+            signer: None,
+            key: None,
+            output: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        };
+
+        for line in status_output.lines() {
+            let Some(rest) = line.strip_prefix("[GNUPG:] ") else { continue };
+            let mut parts = rest.splitn(3, ' ');
+            let keyword = parts.next().unwrap_or("");
+            let key = parts.next().map(|s| s.to_string());
+            let signer = parts.next().map(|s| s.to_string());
+
+            let status = match keyword {
+                "GOODSIG" => "good",
+                "BADSIG" => "bad",
+                "EXPSIG" | "EXPKEYSIG" => "expired",
+                "REVKEYSIG" => "revoked",
+                "ERRSIG" => "error",
+                // Good signature from a key the local trust database does not vouch for
+                "TRUST_UNDEFINED" | "TRUST_NEVER" if result.status == "good" => "untrusted",
+                _ => continue,
+            };
+            result.status = status.to_string();
+            if keyword != "TRUST_UNDEFINED" && keyword != "TRUST_NEVER" {
+                result.key = key;
+                result.signer = signer;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Signer for SshSigner {
+    fn sign(&self, payload: &[u8]) -> Result<String> {
+        let key = self.key.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("user.signingkey must be set for SSH signing"))?;
+
+        // A literal public key means the private half lives in the SSH agent
+        let literal_key = key.strip_prefix("key::").or_else(|| {
+            if key.starts_with("ssh-") || key.starts_with("ecdsa-") || key.starts_with("sk-") {
+                Some(key)
+            } else {
+                None
+            }
+        });
+        let key_file = match literal_key {
+            Some(public_key) => Some(temp_file("pub", public_key.as_bytes())?),
+            None => None,
+        };
+
+        let payload_file = temp_file("payload", payload)?;
+        let key_path = match &key_file {
+            Some(file) => file.path().to_string_lossy().to_string(),
+            None => expand_home(key).to_string_lossy().to_string(),
+        };
+        let payload_path = payload_file.path().to_string_lossy().to_string();
+
+        let mut args = vec!["-Y", "sign", "-n", "git", "-f", key_path.as_str()];
+        if key_file.is_some() {
+            args.push("-U");
+        }
+        args.push(payload_path.as_str());
+
+        let output = Command::new(resolve_program(&self.program))
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .context(format!("Failed to run {}", self.program))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} failed to sign the data: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let sig_path = PathBuf::from(format!("{}.sig", payload_path));
+        let signature = std::fs::read_to_string(&sig_path)
+            .context("Failed to read SSH signature");
+        let _ = std::fs::remove_file(&sig_path);
+        signature
+    }
+
+    fn verify(&self, payload: &[u8], signature: &str) -> Result<SignatureVerification> {
+        let sig_file = temp_file("sig", signature.as_bytes())?;
+        let sig_path = sig_file.path().to_string_lossy().to_string();
+
+        let mut result = SignatureVerification {
+            status: "error".to_string(),
+            format: Some("ssh".to_string()),
+            signer: None,
+            key: None,
+            output: None,
+        };
+
+        // Without an allowed signers file we can only check the signature is intact
+        let Some(allowed) = &self.allowed_signers else {
+            let args = ["-Y", "check-novalidate", "-n", "git", "-s", sig_path.as_str()];
+            let output = run_with_stdin(&self.program, &args, payload)?;
+            result.status = if output.status.success() { "untrusted" } else { "bad" }.to_string();
+            result.output = Some(combined_output(&output));
+            return Ok(result);
+        };
+        let allowed_path = expand_home(&allowed.to_string_lossy()).to_string_lossy().to_string();
+
+        let find = Command::new(resolve_program(&self.program))
+            .args(["-Y", "find-principals", "-f", allowed_path.as_str(), "-s", sig_path.as_str()])
+            .output()
+            .context(format!("Failed to run {}", self.program))?;
+        let principal = String::from_utf8_lossy(&find.stdout)
+            .lines()
+            .next()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        let Some(principal) = principal else {
+            let args = ["-Y", "check-novalidate", "-n", "git", "-s", sig_path.as_str()];
+            let output = run_with_stdin(&self.program, &args, payload)?;
+            result.status = if output.status.success() { "untrusted" } else { "bad" }.to_string();
+            result.output = Some(combined_output(&output));
+            return Ok(result);
+        };
+
+        let args = [
+            "-Y", "verify",
+            "-f", allowed_path.as_str(),
+            "-I", principal.as_str(),
+            "-n", "git",
+            "-s", sig_path.as_str(),
+        ];
+        let output = run_with_stdin(&self.program, &args, payload)?;
+        let text = combined_output(&output);
+
+        result.status = if output.status.success() { "good" } else { "bad" }.to_string();
+        result.signer = Some(principal);
+        // "Good "git" signature for <principal> with ED25519 key SHA256:..."
+        result.key = text.split_whitespace()
+            .find(|word| word.starts_with("SHA256:"))
+            .map(|s| s.to_string());
+        result.output = Some(text);
+        Ok(result)
+    }
+}
+
+impl GitRepository {
+    /// Get the signer to use for new commits, if `commit.gpgsign` is enabled
+    pub(crate) fn commit_signer(&self) -> Result<Option<Box<dyn Signer>>> {
+        let config = self.repo.config()?;
+        if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+            return Ok(None);
+        }
+        let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+        self.signer_for_format(&format).map(Some)
+    }
+
+    /// Get the signer to use for new annotated tags, if `tag.gpgsign` is enabled
+    pub(crate) fn tag_signer(&self) -> Result<Option<Box<dyn Signer>>> {
+        let config = self.repo.config()?;
+        if !config.get_bool("tag.gpgsign").unwrap_or(false) {
+            return Ok(None);
+        }
+        let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+        self.signer_for_format(&format).map(Some)
+    }
+
+    /// Build the signer backend for a `gpg.format` value
+    fn signer_for_format(&self, format: &str) -> Result<Box<dyn Signer>> {
+        let config = self.repo.config()?;
+        let key = config.get_string("user.signingkey").ok().filter(|k| !k.trim().is_empty());
+
+        match format {
+            "openpgp" => Ok(Box::new(GpgSigner {
+                program: config.get_string("gpg.openpgp.program")
+                    .or_else(|_| config.get_string("gpg.program"))
+                    .unwrap_or_else(|_| "gpg".to_string()),
+                key,
+                format: "openpgp",
+            })),
+            "x509" => Ok(Box::new(GpgSigner {
+                program: config.get_string("gpg.x509.program").unwrap_or_else(|_| "gpgsm".to_string()),
+                key,
+                format: "x509",
+            })),
+            "ssh" => Ok(Box::new(SshSigner {
+                program: config.get_string("gpg.ssh.program").unwrap_or_else(|_| "ssh-keygen".to_string()),
+                key,
+                allowed_signers: config.get_path("gpg.ssh.allowedSignersFile").ok(),
+            })),
+            _ => anyhow::bail!("Unsupported gpg.format: {}", format),
+        }
+    }
+
+    /// Create a commit, signing it when `commit.gpgsign` is enabled
+    ///
+    /// When `update_ref` is given the reference (or the branch HEAD points to) is
+    /// moved to the new commit. Unlike `Repository::commit` the first parent does not
+    /// have to be the current tip, so this also serves amend.
+    pub(crate) fn create_commit(
+        &self,
+        update_ref: Option<&str>,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: &[&Commit],
+    ) -> Result<Oid> {
+        let oid = match self.commit_signer()? {
+            Some(signer) => {
+                let buffer = self.repo.commit_create_buffer(author, committer, message, tree, parents)?;
+                let content = buffer.as_str()
+                    .ok_or_else(|| anyhow::anyhow!("Commit content is not valid UTF-8"))?;
+                let signature = signer.sign(&buffer).context("Failed to sign commit")?;
+                self.repo.commit_signed(content, &signature, None)?
+            }
+            None => self.repo.commit(None, author, committer, message, tree, parents)?,
+        };
+
+        if let Some(refname) = update_ref {
+            let summary = message.lines().next().unwrap_or("");
+            // A commit that does not build on the current tip replaces it, as amend does
+            let current = self.repo.refname_to_id(refname).ok();
+            let reflog = if current.is_some_and(|tip| parents.iter().all(|p| p.id() != tip)) {
+                format!("commit (amend): {}", summary)
+            } else if parents.is_empty() {
+                format!("commit (initial): {}", summary)
+            } else if parents.len() > 1 {
+                format!("commit (merge): {}", summary)
+            } else {
+                format!("commit: {}", summary)
+            };
+            self.update_reference(refname, oid, &reflog)?;
+        }
+
+        Ok(oid)
+    }
+
+    /// Point a reference at `oid`, following HEAD to its branch when it is symbolic
+    pub(crate) fn update_reference(&self, refname: &str, oid: Oid, log_message: &str) -> Result<()> {
+        let mut target = refname.to_string();
+        if let Ok(reference) = self.repo.find_reference(refname) {
+            if let Some(symbolic) = reference.symbolic_target() {
+                target = symbolic.to_string();
+            }
+        }
+
+        if target == "HEAD" {
+            self.repo.set_head_detached(oid)?;
+        } else {
+            self.repo.reference(&target, oid, true, log_message)?;
+        }
+        Ok(())
+    }
+
+    /// Create an annotated tag object signed with the configured tag signer
+    pub(crate) fn create_signed_tag(
+        &self,
+        tag_name: &str,
+        target: &Commit,
+        tagger: &Signature,
+        message: &str,
+        signer: &dyn Signer,
+    ) -> Result<Oid> {
+        let refname = format!("refs/tags/{}", tag_name);
+        if !git2::Reference::is_valid_name(&refname) {
+            anyhow::bail!("Invalid tag name: {}", tag_name);
+        }
+
+        let mut buffer = format!(
+            "object {}\ntype commit\ntag {}\ntagger {}\n\n{}",
+            target.id(),
+            tag_name,
+            format_signature(tagger),
+            message
+        );
+        if !buffer.ends_with('\n') {
+            buffer.push('\n');
+        }
+
+        let signature = signer.sign(buffer.as_bytes()).context("Failed to sign tag")?;
+        buffer.push_str(&signature);
+
+        let oid = self.repo.odb()?.write(git2::ObjectType::Tag, buffer.as_bytes())?;
+        self.repo.reference(&refname, oid, false, "tag: signed")?;
+        Ok(oid)
+    }
+
+    /// Verify the signature of a commit
+    pub fn verify_commit_signature(&self, commit_hash: &str) -> Result<SignatureVerification> {
+        let commit = self.repo.revparse_single(commit_hash)
+            .context(format!("Failed to find commit: {}", commit_hash))?
+            .peel_to_commit()?;
+
+        // SHA-256 repositories sign commits under `gpgsig-sha256` instead of `gpgsig`
+        let extracted = self.repo.extract_signature(&commit.id(), None)
+            .or_else(|_| self.repo.extract_signature(&commit.id(), Some("gpgsig-sha256")));
+        let (signature, signed_data) = match extracted {
+            Ok(parts) => parts,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(SignatureVerification {
+                    status: "unsigned".to_string(),
+                    format: None,
+                    signer: None,
+                    key: None,
+                    output: None,
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let signature = signature.as_str()
+            .ok_or_else(|| anyhow::anyhow!("Signature is not valid UTF-8"))?;
+        let format = if signature.starts_with("-----BEGIN SSH SIGNATURE-----") {
+            "ssh"
+        } else if signature.starts_with("-----BEGIN SIGNED MESSAGE-----") {
+            "x509"
+        } else {
+            "openpgp"
+        };
+
+        self.signer_for_format(format)?.verify(&signed_data, signature)
+    }
+}
+
+/// Format a signature the way it appears in commit and tag headers
+fn format_signature(signature: &Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().unwrap_or(""),
+        signature.email().unwrap_or(""),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+/// Run a program, feeding `input` on stdin and collecting its output
+fn run_with_stdin(program: &str, args: &[&str], input: &[u8]) -> Result<std::process::Output> {
+    let mut child = Command::new(resolve_program(program))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("Failed to run {}. Is it installed?", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).context(format!("Failed to write to {}", program))?;
+    }
+
+    child.wait_with_output().context(format!("Failed to wait for {}", program))
+}
+
+fn combined_output(output: &std::process::Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+    .trim()
+    .to_string()
+}
+
+/// Find a program by bare name, also looking in Homebrew/MacPorts locations
+///
+/// Apps launched from Finder do not inherit the shell PATH, so `gpg` installed
+/// through Homebrew would otherwise not be found.
+pub(crate) fn resolve_program(program: &str) -> PathBuf {
+    if program.contains('/') || program.contains('\\') {
+        return expand_home(program);
+    }
+
+    let in_path = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false);
+    if !in_path {
+        for dir in ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"] {
+            let candidate = Path::new(dir).join(program);
+            if candidate.is_file() {
+                return candidate;
+            }
+        }
+    }
+
+    PathBuf::from(program)
+}
+
+/// Expand a leading `~/` to the user's home directory
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = home::home_dir() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// A temporary file holding `content`, removed when dropped
+///
+/// The file is created exclusively with a random name and owner-only permissions.
+fn temp_file(suffix: &str, content: &[u8]) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("caogit-")
+        .suffix(&format!(".{}", suffix))
+        .tempfile()
+        .context("Failed to create temporary file")?;
+    file.write_all(content)
+        .and_then(|_| file.flush())
+        .context("Failed to write temporary file")?;
+    Ok(file)
+}
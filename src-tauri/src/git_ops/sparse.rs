@@ -0,0 +1,286 @@
+//! Sparse checkout
+//!
+//! This module reads and edits the sparse-checkout patterns of a repository.
+//! libgit2 does not support sparse checkout, so changes are delegated to the
+//! `git` command while the current state is read natively. While it is on,
+//! resets and checkouts elsewhere go through `git` as well.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, Commit, Config, IndexEntryExtendedFlag, Repository, ResetType};
+
+use super::repository::GitRepository;
+use super::types::SparseCheckoutInfo;
+
+impl GitRepository {
+    /// Current sparse-checkout mode and patterns
+    pub fn get_sparse_checkout(&self) -> Result<SparseCheckoutInfo> {
+        let config = self.sparse_config()?;
+        let enabled = config.get_bool("core.sparseCheckout").unwrap_or(false);
+        let cone = enabled && config.get_bool("core.sparseCheckoutCone").unwrap_or(true);
+
+        let patterns: Vec<String> = fs::read_to_string(self.repo.path().join("info").join("sparse-checkout"))
+            .map(|content| {
+                content.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let directories = if cone { cone_directories(&patterns) } else { Vec::new() };
+
+        Ok(SparseCheckoutInfo { enabled, cone, directories, patterns })
+    }
+
+    /// Enable sparse checkout and replace the patterns
+    ///
+    /// In cone mode `patterns` are directories; otherwise they are gitignore-style patterns.
+    pub fn set_sparse_checkout(&self, patterns: &[String], cone: bool) -> Result<SparseCheckoutInfo> {
+        let patterns = if cone { normalize_directories(patterns) } else { patterns.to_vec() };
+        let mut args = vec!["sparse-checkout", "set", if cone { "--cone" } else { "--no-cone" }, "--"];
+        args.extend(patterns.iter().map(String::as_str));
+        self.run_git_in_workdir(&args)?;
+        self.get_sparse_checkout()
+    }
+
+    /// Add directories to a cone-mode sparse checkout
+    pub fn add_sparse_directories(&self, directories: &[String]) -> Result<SparseCheckoutInfo> {
+        let info = self.get_sparse_checkout()?;
+        if !info.enabled {
+            return self.set_sparse_checkout(directories, true);
+        }
+        if !info.cone {
+            anyhow::bail!("Sparse checkout is not in cone mode; edit its patterns instead");
+        }
+
+        let directories = normalize_directories(directories);
+        let mut args = vec!["sparse-checkout", "add", "--"];
+        args.extend(directories.iter().map(String::as_str));
+        self.run_git_in_workdir(&args)?;
+        self.get_sparse_checkout()
+    }
+
+    /// Remove directories (and everything below them) from a cone-mode sparse checkout
+    pub fn remove_sparse_directories(&self, directories: &[String]) -> Result<SparseCheckoutInfo> {
+        let info = self.get_sparse_checkout()?;
+        if !info.enabled || !info.cone {
+            anyhow::bail!("Sparse checkout is not enabled in cone mode");
+        }
+
+        let removed = normalize_directories(directories);
+        let remaining: Vec<String> = info.directories.into_iter()
+            .filter(|dir| !removed.iter().any(|r| dir == r || dir.starts_with(&format!("{}/", r))))
+            .collect();
+        self.set_sparse_checkout(&remaining, true)
+    }
+
+    /// Update the working directory to match the sparse-checkout patterns again
+    pub fn reapply_sparse_checkout(&self) -> Result<()> {
+        self.run_git_in_workdir(&["sparse-checkout", "reapply"])?;
+        Ok(())
+    }
+
+    /// Turn sparse checkout off and check out every file
+    pub fn disable_sparse_checkout(&self) -> Result<()> {
+        self.run_git_in_workdir(&["sparse-checkout", "disable"])?;
+        Ok(())
+    }
+
+    /// Whether sparse checkout is turned on
+    pub(crate) fn is_sparse_checkout(&self) -> bool {
+        self.sparse_config()
+            .and_then(|config| Ok(config.get_bool("core.sparseCheckout")?))
+            .unwrap_or(false)
+    }
+
+    /// Whether a path is left out of the working directory by sparse checkout
+    pub(crate) fn is_sparse_excluded(&self, path: &str) -> bool {
+        let Ok(mut index) = self.repo.index() else { return false };
+        // The `git` command may have rewritten the index behind the cached one
+        if index.read(false).is_err() {
+            return false;
+        }
+        index.get_path(Path::new(path), 0)
+            .is_some_and(|entry| entry.flags_extended & IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0)
+    }
+
+    /// Switch branches with the `git` command so only the sparse cone is written
+    pub(crate) fn checkout_sparse_branch(&self, name: &str) -> Result<()> {
+        self.repo.find_branch(name, BranchType::Local)
+            .context(format!("Branch '{}' not found", name))?;
+        self.run_git_in_workdir(&["checkout", "-q", name, "--"])?;
+        Ok(())
+    }
+
+    /// Move HEAD like `git reset`, through the `git` command while sparse checkout is on
+    ///
+    /// libgit2 would rewrite the index without the skip-worktree bits and write
+    /// out every file, including the ones left out of the sparse cone.
+    pub(crate) fn reset_head(&self, commit: &Commit, reset_type: ResetType) -> Result<()> {
+        if !self.is_sparse_checkout() {
+            self.repo.reset(commit.as_object(), reset_type, None)?;
+            return Ok(());
+        }
+        let mode = match reset_type {
+            ResetType::Soft => "--soft",
+            ResetType::Mixed => "--mixed",
+            ResetType::Hard => "--hard",
+        };
+        self.run_git_in_workdir(&["reset", "-q", mode, &commit.id().to_string()])?;
+        Ok(())
+    }
+
+    /// Check out `commit` on a detached HEAD, through the `git` command while sparse checkout is on
+    pub(crate) fn checkout_detached(&self, commit: &Commit, checkout: Option<&mut CheckoutBuilder>) -> Result<()> {
+        if self.is_sparse_checkout() {
+            self.run_git_in_workdir(&["checkout", "-q", "--detach", &commit.id().to_string()])?;
+            return Ok(());
+        }
+        self.repo.checkout_tree(commit.as_object(), checkout)?;
+        self.repo.set_head_detached(commit.id())?;
+        Ok(())
+    }
+
+    /// Make the index and working directory match HEAD after moving a branch under it
+    pub(crate) fn force_checkout_head(&self) -> Result<()> {
+        if self.is_sparse_checkout() {
+            self.run_git_in_workdir(&["reset", "-q", "--hard", "HEAD"])?;
+            return Ok(());
+        }
+        self.repo.checkout_head(Some(CheckoutBuilder::default().force()))?;
+        Ok(())
+    }
+
+    /// Configuration including `config.worktree`
+    ///
+    /// `git sparse-checkout` stores its settings there after turning on
+    /// `extensions.worktreeConfig`, which an already opened repository does not pick up.
+    fn sparse_config(&self) -> Result<Config> {
+        Ok(Repository::open(self.repo.path())?.config()?)
+    }
+
+    fn run_git_in_workdir(&self, args: &[&str]) -> Result<String> {
+        let workdir = self.repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?;
+        run_git(workdir, args)
+    }
+}
+
+/// Run `git` in `dir` and return its standard output
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args[..2.min(args.len())].join(" "), stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Directories checked out recursively by a cone-mode pattern set
+///
+/// Cone patterns list every parent as `/dir/` followed by `!/dir/*/`; the
+/// directories without such an exclusion are the ones that were requested.
+fn cone_directories(patterns: &[String]) -> Vec<String> {
+    patterns.iter()
+        .filter(|p| p.starts_with('/') && p.ends_with('/') && p.len() > 1)
+        .filter(|p| !patterns.contains(&format!("!{}*/", p)))
+        .map(|p| p.trim_matches('/').to_string())
+        .collect()
+}
+
+fn normalize_directories(directories: &[String]) -> Vec<String> {
+    directories.iter()
+        .map(|dir| dir.replace('\\', "/").trim_matches('/').to_string())
+        .filter(|dir| !dir.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cone_directories() {
+        let patterns = strings(&["/*", "!/*/", "/src/", "!/src/*/", "/src/app/", "/docs/"]);
+        assert_eq!(cone_directories(&patterns), strings(&["src/app", "docs"]));
+    }
+
+    #[test]
+    fn test_normalize_directories() {
+        let directories = strings(&["/src/", "docs\\api", "", "/"]);
+        assert_eq!(normalize_directories(&directories), strings(&["src", "docs/api"]));
+    }
+
+    #[test]
+    fn test_set_and_remove_sparse_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [&["init", "-q"][..], &["config", "user.name", "Test"], &["config", "user.email", "test@example.com"]] {
+            run_git(dir.path(), args).unwrap();
+        }
+        for path in ["a/one.txt", "b/two.txt", "top.txt"] {
+            let full = dir.path().join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, "x\n").unwrap();
+        }
+        run_git(dir.path(), &["add", "."]).unwrap();
+        run_git(dir.path(), &["commit", "-qm", "init"]).unwrap();
+
+        let repo = GitRepository::open(dir.path().to_str().unwrap()).unwrap();
+        let info = repo.set_sparse_checkout(&strings(&["a", "b"]), true).unwrap();
+        assert!(info.enabled && info.cone);
+        assert_eq!(info.directories, strings(&["a", "b"]));
+
+        let info = repo.remove_sparse_directories(&strings(&["b"])).unwrap();
+        assert_eq!(info.directories, strings(&["a"]));
+        assert!(dir.path().join("a/one.txt").exists() && dir.path().join("top.txt").exists());
+        assert!(!dir.path().join("b/two.txt").exists());
+        assert!(repo.is_sparse_excluded("b/two.txt"));
+
+        repo.disable_sparse_checkout().unwrap();
+        assert!(dir.path().join("b/two.txt").exists());
+        assert!(!repo.is_sparse_checkout());
+    }
+
+    #[test]
+    fn test_sparse_excluded_files_are_not_changes() {
+        let (dir, repo) = init_repo();
+        commit_file(&repo, "a/one.txt", "1\n", "first");
+        commit_file(&repo, "b/two.txt", "2\n", "second");
+        repo.set_sparse_checkout(&strings(&["a"]), true).unwrap();
+        assert!(!dir.path().join("b/two.txt").exists());
+
+        // Clean-tree checks ignore the excluded files
+        repo.bisect_start("HEAD", &["HEAD~1".to_string()]).unwrap();
+        repo.bisect_reset().unwrap();
+        assert!(!dir.path().join("b/two.txt").exists());
+
+        // A hard reset keeps them out and does not snapshot them as deleted
+        fs::write(dir.path().join("a/one.txt"), "dirty\n").unwrap();
+        let result = repo.reset("HEAD", "hard").unwrap();
+        assert!(!dir.path().join("b/two.txt").exists());
+        assert!(repo.is_sparse_excluded("b/two.txt"));
+        let snapshot = repo.repo.find_commit(git2::Oid::from_str(&result.snapshot.unwrap()).unwrap()).unwrap();
+        assert!(snapshot.tree().unwrap().get_path(Path::new("b/two.txt")).is_ok());
+
+        repo.undo_hard_reset(&result.previous_head, Some(&snapshot.id().to_string())).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("a/one.txt")).unwrap(), "dirty\n");
+        assert!(!dir.path().join("b/two.txt").exists());
+        assert!(repo.is_sparse_excluded("b/two.txt"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Submodule operations
+//!
+//! This module lists submodules with their recorded and checked-out commits,
+//! initialises, updates and syncs them (optionally recursively), and resolves
+//! a submodule pointer change into the range of commits it covers.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use git2::{Config, FetchOptions, Oid, Submodule, SubmoduleIgnore, SubmoduleStatus, SubmoduleUpdateOptions};
+
+use super::hooks::HookOptions;
+use super::credentials::{credential_callbacks, CredentialApproval};
+use super::repository::GitRepository;
+use super::types::{AuthConfig, CommitInfo, SubmoduleChange, SubmoduleDiff, SubmoduleInfo};
+
+impl GitRepository {
+    /// List submodules, descending into checked-out ones when `recursive` is set
+    pub fn list_submodules(&self, recursive: bool) -> Result<Vec<SubmoduleInfo>> {
+        let mut submodules = Vec::new();
+        self.collect_submodules("", recursive, &mut submodules)?;
+        Ok(submodules)
+    }
+
+    /// Register submodules in `.git/config` without cloning them
+    ///
+    /// An empty `paths` selects every submodule.
+    pub fn init_submodules(&self, paths: &[String]) -> Result<Vec<String>> {
+        let mut initialized = Vec::new();
+        for mut submodule in self.selected_submodules(paths)? {
+            submodule.init(false)
+                .context(format!("Failed to initialize submodule {}", submodule.path().display()))?;
+            initialized.push(submodule.path().display().to_string());
+        }
+        Ok(initialized)
+    }
+
+    /// Clone missing submodules and check out the commits the index records
+    ///
+    /// With `init` uninitialised submodules are registered first; with `recursive`
+    /// nested submodules are updated too. Returns the updated paths.
+    pub fn update_submodules(
+        &self,
+        paths: &[String],
+        init: bool,
+        recursive: bool,
+        auth_config: Option<AuthConfig>,
+    ) -> Result<Vec<String>> {
+        let mut updated = Vec::new();
+        self.update_submodules_in("", paths, init, recursive, &auth_config, &mut updated)?;
+        Ok(updated)
+    }
+
+    /// Copy submodule URLs from `.gitmodules` into the configuration of each submodule
+    pub fn sync_submodules(&self, recursive: bool) -> Result<Vec<String>> {
+        let mut synced = Vec::new();
+        self.sync_submodules_in("", recursive, &mut synced)?;
+        Ok(synced)
+    }
+
+    /// Commits between the old and new pointer of a submodule
+    ///
+    /// Staged compares HEAD with the index; unstaged compares the index with the
+    /// commit checked out in the submodule.
+    pub fn get_submodule_diff(&self, path: &str, staged: bool) -> Result<SubmoduleDiff> {
+        let submodule = self.repo.find_submodule(path)
+            .context(format!("Submodule {} not found", path))?;
+        let change = self.submodule_change(path)?;
+        let (old_commit, new_commit) = if staged {
+            (change.head_commit, change.index_commit)
+        } else {
+            (change.index_commit, change.workdir_commit)
+        };
+
+        let sub_repo = submodule.open()
+            .context(format!("Submodule {} is not checked out", path))?;
+        let old = old_commit.as_deref().map(Oid::from_str).transpose()?;
+        let new = new_commit.as_deref().map(Oid::from_str).transpose()?;
+        let sub = GitRepository { repo: sub_repo, hooks: HookOptions::default() };
+
+        let added = match new {
+            Some(new) => sub.commit_range(new, old)
+                .context(format!("Commits of submodule {} are missing; update it first", path))?,
+            None => Vec::new(),
+        };
+        let removed = match (old, new) {
+            (Some(old), Some(new)) => sub.commit_range(old, Some(new))
+                .context(format!("Commits of submodule {} are missing; update it first", path))?,
+            _ => Vec::new(),
+        };
+
+        Ok(SubmoduleDiff {
+            path: path.to_string(),
+            old_commit,
+            new_commit,
+            added,
+            removed,
+            dirty: change.dirty,
+        })
+    }
+
+    /// Paths of the submodules configured in this repository
+    pub(crate) fn submodule_paths(&self) -> HashSet<String> {
+        self.repo.submodules()
+            .map(|submodules| {
+                submodules.iter()
+                    .map(|s| s.path().to_string_lossy().replace('\\', "/"))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Pointers of a submodule in HEAD, the index and its working directory
+    pub(crate) fn submodule_change(&self, path: &str) -> Result<SubmoduleChange> {
+        let submodule = self.repo.find_submodule(path)?;
+        let name = submodule.name().unwrap_or(path).to_string();
+        let status = self.repo.submodule_status(&name, SubmoduleIgnore::None)?;
+
+        Ok(SubmoduleChange {
+            head_commit: submodule.head_id().map(|id| id.to_string()),
+            index_commit: submodule.index_id().map(|id| id.to_string()),
+            workdir_commit: submodule.workdir_id().map(|id| id.to_string()),
+            dirty: is_dirty(status),
+        })
+    }
+
+    fn collect_submodules(&self, prefix: &str, recursive: bool, out: &mut Vec<SubmoduleInfo>) -> Result<()> {
+        for submodule in self.repo.submodules()? {
+            let name = submodule.name().unwrap_or("").to_string();
+            let path = format!("{}{}", prefix, submodule.path().to_string_lossy().replace('\\', "/"));
+            let status = self.repo.submodule_status(&name, SubmoduleIgnore::None)?;
+            let recorded = submodule.index_id().or(submodule.head_id());
+            let checked_out = status.contains(SubmoduleStatus::IN_WD)
+                && !status.contains(SubmoduleStatus::WD_UNINITIALIZED);
+
+            out.push(SubmoduleInfo {
+                name,
+                path: path.clone(),
+                url: submodule.url().map(String::from),
+                branch: submodule.branch().map(String::from),
+                recorded_commit: recorded.map(|id| id.to_string()),
+                checked_out_commit: submodule.workdir_id().map(|id| id.to_string()),
+                initialized: status.contains(SubmoduleStatus::IN_CONFIG),
+                checked_out,
+                dirty: is_dirty(status),
+                out_of_date: checked_out && recorded != submodule.workdir_id(),
+            });
+
+            if recursive && checked_out {
+                if let Ok(repo) = submodule.open() {
+                    let nested = GitRepository { repo, hooks: HookOptions::default() };
+                    nested.collect_submodules(&format!("{}/", path), true, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn update_submodules_in(
+        &self,
+        prefix: &str,
+        paths: &[String],
+        init: bool,
+        recursive: bool,
+        auth_config: &Option<AuthConfig>,
+        updated: &mut Vec<String>,
+    ) -> Result<()> {
+        for mut submodule in self.selected_submodules(paths)? {
+            let path = format!("{}{}", prefix, submodule.path().to_string_lossy().replace('\\', "/"));
+
+            let (fetch_options, credentials) = submodule_fetch_options(auth_config.clone(), self.repo.config().ok());
+            let mut opts = SubmoduleUpdateOptions::new();
+            opts.fetch(fetch_options);
+            submodule.update(init, Some(&mut opts))
+                .context(format!("Failed to update submodule {}", path))?;
+            credentials.approve();
+            updated.push(path.clone());
+
+            if recursive {
+                let repo = submodule.open()
+                    .context(format!("Failed to open submodule {}", path))?;
+                let nested = GitRepository { repo, hooks: HookOptions::default() };
+                nested.update_submodules_in(&format!("{}/", path), &[], init, true, auth_config, updated)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_submodules_in(&self, prefix: &str, recursive: bool, synced: &mut Vec<String>) -> Result<()> {
+        for mut submodule in self.repo.submodules()? {
+            let path = format!("{}{}", prefix, submodule.path().to_string_lossy().replace('\\', "/"));
+            submodule.sync()
+                .context(format!("Failed to sync submodule {}", path))?;
+            synced.push(path.clone());
+
+            if recursive {
+                if let Ok(repo) = submodule.open() {
+                    let nested = GitRepository { repo, hooks: HookOptions::default() };
+                    nested.sync_submodules_in(&format!("{}/", path), true, synced)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Submodules whose path is in `paths`, or all of them when it is empty
+    fn selected_submodules(&self, paths: &[String]) -> Result<Vec<Submodule<'_>>> {
+        let submodules = self.repo.submodules()?;
+        if paths.is_empty() {
+            return Ok(submodules);
+        }
+
+        let selected: Vec<Submodule> = submodules.into_iter()
+            .filter(|s| paths.iter().any(|p| Some(p.trim_end_matches('/')) == s.path().to_str()))
+            .collect();
+        if selected.len() != paths.len() {
+            anyhow::bail!("Some of the given paths are not submodules: {}", paths.join(", "));
+        }
+        Ok(selected)
+    }
+
+    /// Commits reachable from `tip` but not from `hide`, newest first
+    fn commit_range(&self, tip: Oid, hide: Option<Oid>) -> Result<Vec<CommitInfo>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(tip)?;
+        if let Some(hide) = hide {
+            revwalk.hide(hide)?;
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(self.commit_info(&commit));
+        }
+        Ok(commits)
+    }
+}
+
+/// Whether a submodule has modified content or untracked files
+fn is_dirty(status: SubmoduleStatus) -> bool {
+    status.intersects(
+        SubmoduleStatus::WD_INDEX_MODIFIED
+            | SubmoduleStatus::WD_WD_MODIFIED
+            | SubmoduleStatus::WD_UNTRACKED,
+    )
+}
+
+/// Fetch options for cloning and fetching submodules
+fn submodule_fetch_options<'a>(
+    auth_config: Option<AuthConfig>,
+    config: Option<Config>,
+) -> (FetchOptions<'a>, CredentialApproval) {
+    let (callbacks, credentials) = credential_callbacks(auth_config, config);
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    (fetch_options, credentials)
+}
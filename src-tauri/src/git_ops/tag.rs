@@ -15,7 +15,14 @@ impl GitRepository {
 
         if let Some(msg) = message {
             let signature = self.repo.signature()?;
-            self.repo.tag(tag_name, target.as_object(), &signature, msg, false)?;
+            match self.tag_signer()? {
+                Some(signer) => {
+                    self.create_signed_tag(tag_name, &target, &signature, msg, signer.as_ref())?;
+                }
+                None => {
+                    self.repo.tag(tag_name, target.as_object(), &signature, msg, false)?;
+                }
+            }
         } else {
             self.repo.tag_lightweight(tag_name, target.as_object(), false)?;
         }
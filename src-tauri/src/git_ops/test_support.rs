@@ -0,0 +1,29 @@
+//! Shared fixtures for the `git_ops` unit tests
+
+use std::fs;
+
+use super::repository::GitRepository;
+
+/// Empty repository with a committer identity configured
+pub(super) fn init_repo() -> (tempfile::TempDir, GitRepository) {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let raw = git2::Repository::init(dir.path()).unwrap();
+        let mut config = raw.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+    }
+    let repo = GitRepository::open(dir.path().to_str().unwrap()).unwrap();
+    (dir, repo)
+}
+
+/// Write `content` to `name`, stage it and commit; returns the new commit id
+pub(super) fn commit_file(repo: &GitRepository, name: &str, content: &str, message: &str) -> String {
+    let path = repo.repo.workdir().unwrap().join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(path, content).unwrap();
+    repo.stage_file(name).unwrap();
+    repo.commit(message).unwrap()
+}
@@ -10,6 +10,7 @@ pub struct FileChange {
     pub path: String,
     pub status: String,
     pub staged: bool,
+    pub submodule: Option<SubmoduleChange>, // status 为 "submodule" 时的指针变化
 }
 
 /// Information about a commit
@@ -21,6 +22,7 @@ pub struct CommitInfo {
     pub email: String,
     pub date: String,
     pub parents: Vec<String>,
+    pub signed: bool,
 }
 
 /// Information about a branch
@@ -31,6 +33,7 @@ pub struct BranchInfo {
     pub is_remote: bool,
     pub upstream: Option<String>,
     pub last_commit: Option<String>,
+    pub worktree: Option<String>, // 已在其他工作树中检出时，该工作树的路径
 }
 
 /// Information about a remote repository
@@ -62,6 +65,19 @@ pub struct DiffResult {
     pub new_path: String,
     pub status: String,
     pub hunks: Vec<DiffHunk>,
+    pub binary: Option<BinaryDiff>, // 二进制文件时不返回 hunks
+    pub truncated: bool,            // 超出行数或文件大小限制，hunks 不完整
+    pub lfs: Option<LfsDiff>,       // Git LFS 文件时两侧的指针，此时 binary 给出真实大小
+}
+
+/// Summary of a binary file change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryDiff {
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+    pub mime_type: Option<String>,   // 仅图片
+    pub old_content: Option<String>, // base64，仅图片
+    pub new_content: Option<String>,
 }
 
 /// A hunk in a diff
@@ -82,6 +98,14 @@ pub struct DiffLine {
     pub content: String,
     pub old_lineno: Option<u32>,
     pub new_lineno: Option<u32>,
+    pub spans: Vec<DiffSpan>, // 与配对行相比发生变化的片段
+}
+
+/// Changed range inside a diff line, in UTF-16 code units so the frontend can slice directly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffSpan {
+    pub start: usize,
+    pub end: usize,
 }
 
 /// Information about a blame line
@@ -131,3 +155,371 @@ pub struct SyncStatus {
     pub ahead: usize,   // 本地领先远程的提交数
     pub behind: usize,  // 本地落后远程的提交数
 }
+
+/// A diff line picked for partial staging, identified by its line numbers
+///
+/// Added lines only carry `new_lineno`, removed lines only carry `old_lineno`,
+/// matching the values returned in `DiffLine`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineSelection {
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+/// An entry of an interactive rebase todo list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseTodoItem {
+    pub action: String, // "pick", "reword", "edit", "squash", "fixup", "drop"
+    pub commit: String,
+    #[serde(default)]
+    pub summary: String,
+    pub message: Option<String>, // 新提交信息（reword 必填，squash 可选）
+}
+
+/// Progress of an interactive rebase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseStatus {
+    pub state: String, // "none", "conflicts", "stopped", "failed", "completed"
+    pub current: Option<RebaseTodoItem>,
+    pub done: usize,
+    pub total: usize,
+    pub head: Option<String>,
+    pub skipped: Vec<String>, // 因改动已存在于上游而未生成提交的 pick
+}
+
+/// Result of verifying a commit or tag signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureVerification {
+    pub status: String, // "unsigned", "good", "untrusted", "bad", "expired", "revoked", "error"
+    pub format: Option<String>, // "openpgp", "ssh", "x509"
+    pub signer: Option<String>,
+    pub key: Option<String>,
+    pub output: Option<String>,
+}
+
+/// A line of output produced by a git hook, emitted as `git-hook-output`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookOutput {
+    pub hook: String,
+    pub stream: String, // "stdout" or "stderr"
+    pub line: String,
+}
+
+/// Filters and pagination for a commit log query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitQuery {
+    pub refs: Vec<String>,         // 起始引用，为空时使用 HEAD
+    pub all_branches: bool,        // 包含所有本地和远程分支
+    pub path: Option<String>,
+    pub follow_renames: bool,
+    pub author: Option<String>,    // 正则，匹配作者名或邮箱（不区分大小写）
+    pub committer: Option<String>, // 正则，匹配提交者名或邮箱（不区分大小写）
+    pub since: Option<String>,     // RFC 3339 或 YYYY-MM-DD
+    pub until: Option<String>,
+    pub grep: Option<String>,      // 正则，匹配提交信息（不区分大小写）
+    pub first_parent: bool,
+    pub order: Option<String>,     // "date"（默认）或 "topo"
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A page of commit log results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitPage {
+    pub commits: Vec<CommitInfo>,
+    pub next_cursor: Option<String>,
+}
+
+/// Author or committer of a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitIdentity {
+    pub name: String,
+    pub email: String,
+    pub date: String,     // RFC 3339，保留原始时区
+    pub timestamp: i64,
+    pub timezone: String, // 例如 "+0800"
+}
+
+/// A `Key: value` trailer at the end of a commit message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitTrailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// A file changed by a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitFileChange {
+    pub path: String,
+    pub old_path: Option<String>, // 重命名或复制的来源路径
+    pub status: String,           // "added", "modified", "deleted", "renamed", "copied", "typechange"
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// Full details of a single commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitDetails {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author: CommitIdentity,
+    pub committer: CommitIdentity,
+    pub subject: String,
+    pub body: String,
+    pub trailers: Vec<CommitTrailer>,
+    pub message: String,
+    pub signature: SignatureVerification,
+    pub files: Vec<CommitFileChange>, // 相对于第一个父提交
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Options controlling how diffs are computed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffSettings {
+    pub whitespace: Option<String>, // "all" (-w), "change" (-b), "eol" (--ignore-space-at-eol)
+    pub context_lines: Option<u32>,
+    pub interhunk_lines: Option<u32>,
+    pub algorithm: Option<String>,  // "myers"（默认）, "minimal", "patience"
+    pub detect_renames: bool,
+    pub detect_copies: bool,
+    pub rename_threshold: Option<u16>, // 相似度百分比，默认 50
+    pub copy_threshold: Option<u16>,
+    pub inline_diff: Option<String>, // "word"（默认）, "char", "none"
+}
+
+/// Options for `GitRepository::clone`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloneOptions {
+    pub branch: Option<String>,                   // 检出的分支，默认为远程 HEAD
+    pub single_branch: bool,                      // 只获取 branch（或远程默认分支）
+    pub depth: Option<u32>,                       // 浅克隆的提交深度
+    pub bare: bool,                               // 不检出工作区
+    pub mirror: bool,                             // 镜像克隆：裸仓库并映射远程的所有引用
+    pub recursive: bool,                          // 同时克隆并检出子模块
+    pub filter: Option<String>,                   // 部分克隆: "blob:none", "tree:0", "blob:limit=<size>"
+    pub sparse_directories: Option<Vec<String>>,  // 锥形稀疏检出，只检出这些目录（及根目录下的文件）
+}
+
+/// Options for `GitRepository::init`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InitOptions {
+    pub default_branch: Option<String>, // 未指定时使用 git 配置中的 init.defaultBranch
+    pub bare: bool,
+    pub template_path: Option<String>,  // 模板目录，未指定时使用 init.templateDir
+    pub initial_commit: bool,           // 将下列文件提交为初始提交；为 false 时只写入文件
+    pub gitignore: Option<String>,      // 项目类型，同 detect_project_type；"auto" 表示自动检测
+    pub readme: bool,
+    pub license: Option<String>,        // "MIT", "ISC", "BSD-3-Clause", "Unlicense"
+    pub name: Option<String>,           // README 标题，默认为目录名
+    pub description: Option<String>,
+}
+
+/// A patch produced by `format_patch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFile {
+    pub file_name: String, // 例如 "0001-fix-typo.patch"
+    pub commit: String,
+    pub content: String,   // mbox 格式
+}
+
+/// Outcome of moving the current branch with `reset`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResetResult {
+    pub previous_head: String,
+    pub new_head: String,
+    pub snapshot: Option<String>, // hard 模式下被丢弃改动的快照提交（refs/caogit/reset-snapshots/），可用于撤销
+}
+
+/// One entry of a reference's reflog, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflogEntry {
+    pub index: usize,          // 对应 ref@{index}
+    pub old_id: String,
+    pub new_id: String,
+    pub committer: String,
+    pub email: String,
+    pub timestamp: i64,
+    pub message: String,
+}
+
+/// Where HEAD pointed at a given moment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeadState {
+    pub branch: Option<String>, // 符号引用的目标（如 refs/heads/main），分离 HEAD 时为 None
+    pub commit: Option<String>, // 未出生的分支为 None
+}
+
+/// A local branch that an operation created, moved or deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefChange {
+    pub name: String,
+    pub before: Option<String>, // None 表示操作前不存在
+    pub after: Option<String>,  // None 表示操作后被删除
+}
+
+/// A recorded mutating operation that can be rolled back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub id: i64,
+    pub operation: String,   // commit / merge / checkout / reset / rebase / cherry-pick / revert / branch-delete
+    pub description: String,
+    pub timestamp: i64,
+    pub head_before: HeadState,
+    pub head_after: HeadState,
+    pub refs: Vec<RefChange>,
+    pub in_progress: bool,   // 操作结束时仓库仍处于合并/变基等中间状态
+    #[serde(default)]
+    pub snapshot: Option<String>, // hard reset 前保存的改动快照，撤销时一并恢复
+}
+
+/// A commit no branch or tag reaches any more
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LostCommit {
+    pub commit: CommitInfo,
+    pub source: String,      // 来源：reflog 条目（如 HEAD@{3}）、"stash"（被丢弃的 stash）或 "dangling"
+    pub files: Vec<CommitFileChange>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// State of a bisect session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectStatus {
+    pub active: bool,
+    pub current: Option<String>,      // 当前检出待测试的提交
+    pub bad: Option<String>,
+    pub good: Vec<String>,
+    pub skipped: Vec<String>,
+    pub remaining: usize,             // 仍可能是第一个坏提交的数量
+    pub steps: usize,                 // 预计剩余步数
+    pub first_bad: Option<CommitInfo>,
+    pub only_skipped_left: Vec<String>, // 只剩被跳过的提交时，第一个坏提交在其中
+}
+
+/// One step of an automated bisect run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectRunStep {
+    pub commit: String,
+    pub exit_code: i32,
+    pub verdict: String, // good / bad / skip
+    pub output: String,
+}
+
+/// Result of an automated bisect run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectRunResult {
+    pub status: BisectStatus,
+    pub steps: Vec<BisectRunStep>,
+}
+
+/// A working tree of the repository, the main one or a linked one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeInfo {
+    pub name: String,
+    pub path: String,
+    pub branch: Option<String>, // 分离 HEAD 时为 None
+    pub head: Option<String>,
+    pub is_main: bool,
+    pub is_current: bool,
+    pub locked: bool,
+    pub lock_reason: Option<String>,
+    pub prunable: bool,         // 工作目录已不存在，可以清理
+}
+
+/// Commits a submodule path points at in HEAD, the index and its working directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmoduleChange {
+    pub head_commit: Option<String>,
+    pub index_commit: Option<String>,
+    pub workdir_commit: Option<String>,
+    pub dirty: bool, // 子模块内部有未提交的修改或未跟踪文件
+}
+
+/// Information about a submodule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: String,                      // 相对于顶层仓库的路径（嵌套子模块包含父路径）
+    pub url: Option<String>,
+    pub branch: Option<String>,
+    pub recorded_commit: Option<String>,   // 父仓库索引中记录的提交
+    pub checked_out_commit: Option<String>,
+    pub initialized: bool,
+    pub checked_out: bool,
+    pub dirty: bool,
+    pub out_of_date: bool,                 // 检出的提交与记录的不一致
+}
+
+/// Commits between two pointers of a submodule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmoduleDiff {
+    pub path: String,
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    pub added: Vec<CommitInfo>,   // 新指针包含而旧指针没有的提交
+    pub removed: Vec<CommitInfo>, // 回退时旧指针包含而新指针没有的提交
+    pub dirty: bool,
+}
+
+/// A parsed Git LFS pointer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LfsPointer {
+    pub oid: String,      // sha256
+    pub size: u64,
+    pub downloaded: bool, // 对象已在本地 LFS 存储中
+}
+
+/// Both sides of a change to an LFS-tracked file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LfsDiff {
+    pub old: Option<LfsPointer>,
+    pub new: Option<LfsPointer>, // 工作区中已检出真实内容时为 None
+}
+
+/// LFS setup of a repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LfsInfo {
+    pub tracked_patterns: Vec<String>, // .gitattributes 中 filter=lfs 的模式
+    pub installed: bool,               // git-lfs 是否可用
+}
+
+/// An LFS-tracked file in the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LfsFileInfo {
+    pub path: String,
+    pub oid: String,
+    pub size: u64,
+    pub state: String,    // "smudged"（真实内容）、"pointer"（仅指针）、"missing"
+    pub downloaded: bool,
+}
+
+/// A file lock held on the LFS server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LfsLock {
+    pub id: String,
+    pub path: String,
+    pub owner: String,
+    pub locked_at: String,
+}
+
+/// Sparse-checkout configuration of a repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparseCheckoutInfo {
+    pub enabled: bool,
+    pub cone: bool,               // 锥形模式（按目录）
+    pub directories: Vec<String>, // 锥形模式下完整检出的目录
+    pub patterns: Vec<String>,    // .git/info/sparse-checkout 的原始内容
+}
+
+/// Git credential helpers that apply to a remote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialHelperInfo {
+    pub helpers: Vec<String>,             // credential.helper 的值，按调用顺序
+    pub keychain_helper_installed: bool,  // 全局配置中是否已启用 CaoGit 钥匙串 helper
+}
@@ -0,0 +1,371 @@
+//! Undo journal
+//!
+//! This module records the reference state before and after each mutating
+//! operation in `.git/caogit/undo.json` and rolls the most recent one back.
+//! Commits stay reachable through the reflog, so restoring references is
+//! enough to undo commits, merges, rebases, resets and branch deletions.
+//! Changes discarded by a hard reset come back from its saved snapshot.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use git2::{Oid, RepositoryState, ResetType};
+
+use super::repository::GitRepository;
+use super::types::{HeadState, RefChange, UndoEntry};
+
+/// Oldest entries are dropped beyond this many
+const MAX_UNDO_ENTRIES: usize = 50;
+
+/// HEAD and every local branch at one moment
+struct RefSnapshot {
+    head: HeadState,
+    branches: BTreeMap<String, String>,
+}
+
+impl GitRepository {
+    /// Run a mutating operation and record it in the undo journal
+    ///
+    /// Nothing is recorded when the operation fails or leaves every reference untouched.
+    pub fn record_operation<T>(
+        &mut self,
+        operation: &str,
+        description: &str,
+        run: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let before = self.ref_snapshot()?;
+        let snapshots_before = self.reset_snapshots()?;
+        let result = run(self)?;
+        let after = self.ref_snapshot()?;
+        // A hard reset saves the changes it discards; undoing it must bring them back
+        let snapshot = self.reset_snapshots()?
+            .difference(&snapshots_before)
+            .next()
+            .map(|oid| oid.to_string());
+        let in_progress = self.repo.state() != RepositoryState::Clean || self.rebase_in_progress();
+
+        let mut journal = self.load_undo_journal()?;
+
+        // A conflicted operation finished by continue/commit is one step, not two
+        if let Some(last) = journal.last_mut() {
+            if last.in_progress && last.operation == operation && last.head_after == before.head {
+                let start = snapshot_before_entry(last, &before);
+                last.refs = ref_changes(&start, &after);
+                last.head_after = after.head;
+                last.in_progress = in_progress;
+                if last.snapshot.is_none() {
+                    last.snapshot = snapshot;
+                }
+                // An aborted operation leaves nothing to undo
+                if last.refs.is_empty() && last.head_before == last.head_after {
+                    journal.pop();
+                }
+                self.save_undo_journal(&journal)?;
+                return Ok(result);
+            }
+        }
+
+        let refs = ref_changes(&before, &after);
+        if refs.is_empty() && before.head == after.head && snapshot.is_none() {
+            return Ok(result);
+        }
+
+        let now = chrono::Utc::now();
+        journal.push(UndoEntry {
+            id: now.timestamp_millis(),
+            operation: operation.to_string(),
+            description: description.to_string(),
+            timestamp: now.timestamp(),
+            head_before: before.head,
+            head_after: after.head,
+            refs,
+            in_progress,
+            snapshot,
+        });
+        if journal.len() > MAX_UNDO_ENTRIES {
+            let excess = journal.len() - MAX_UNDO_ENTRIES;
+            journal.drain(..excess);
+        }
+        self.save_undo_journal(&journal)?;
+
+        Ok(result)
+    }
+
+    /// Recorded operations, newest first
+    pub fn get_undo_history(&self) -> Result<Vec<UndoEntry>> {
+        let mut journal = self.load_undo_journal()?;
+        journal.reverse();
+        Ok(journal)
+    }
+
+    /// Roll back the most recent recorded operation
+    ///
+    /// Commits are undone softly so their changes stay staged. Other operations
+    /// restore the working tree too, which needs a clean tree, except for resets
+    /// which fall back to a mixed reset so local changes survive. A hard reset
+    /// with a saved snapshot needs a clean tree to restore the discarded changes.
+    pub fn undo_last_operation(&self) -> Result<UndoEntry> {
+        let mut journal = self.load_undo_journal()?;
+        let entry = journal.pop()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to undo"))?;
+
+        if self.repo.state() != RepositoryState::Clean || self.rebase_in_progress() {
+            anyhow::bail!(
+                "Cannot undo '{}': another operation is in progress. Please finish or abort it first.",
+                entry.description
+            );
+        }
+
+        let current = self.ref_snapshot()?;
+        let unchanged = current.head == entry.head_after
+            && entry.refs.iter().all(|change| current.branches.get(&change.name) == change.after.as_ref());
+        if !unchanged {
+            anyhow::bail!(
+                "Cannot undo '{}': the repository has changed since. Use the reflog to restore it manually.",
+                entry.description
+            );
+        }
+
+        let snapshot = entry.snapshot.as_deref().map(Oid::from_str).transpose()?;
+        if snapshot.is_some_and(|oid| self.repo.find_commit(oid).is_err()) {
+            anyhow::bail!(
+                "Cannot undo '{}': its saved changes were pruned. Use the reflog to restore it manually.",
+                entry.description
+            );
+        }
+        let reset_type = match entry.operation.as_str() {
+            "commit" => ResetType::Soft,
+            _ if snapshot.is_some() => {
+                if self.ensure_clean_worktree("undo").is_err() {
+                    anyhow::bail!(
+                        "Cannot undo '{}': commit or stash your changes first so the changes saved in snapshot {} can be restored.",
+                        entry.description,
+                        entry.snapshot.as_deref().unwrap_or_default()
+                    );
+                }
+                ResetType::Hard
+            }
+            "reset" if self.ensure_clean_worktree("undo").is_err() => ResetType::Mixed,
+            _ => {
+                self.ensure_clean_worktree(&format!("undo '{}'", entry.description))?;
+                ResetType::Hard
+            }
+        };
+
+        let log_message = format!("undo: {}", entry.description);
+
+        // Restore branches that existed before, then HEAD, then drop the ones the operation created
+        for change in &entry.refs {
+            if let Some(before) = &change.before {
+                self.repo.reference(&change.name, Oid::from_str(before)?, true, &log_message)?;
+            }
+        }
+
+        match (&entry.head_before.branch, &entry.head_before.commit) {
+            (Some(branch), _) => self.repo.set_head(branch)?,
+            (None, Some(commit)) => self.repo.set_head_detached(Oid::from_str(commit)?)?,
+            (None, None) => anyhow::bail!("Recorded HEAD state is empty"),
+        }
+
+        if let Some(commit) = &entry.head_before.commit {
+            let commit = self.repo.find_commit(Oid::from_str(commit)?)?;
+            self.reset_head(&commit, reset_type)
+                .context(format!("Failed to undo '{}'", entry.description))?;
+        }
+
+        for change in &entry.refs {
+            if change.before.is_none() {
+                if let Ok(mut reference) = self.repo.find_reference(&change.name) {
+                    reference.delete()?;
+                }
+            }
+        }
+
+        if let Some(snapshot) = snapshot {
+            self.restore_reset_snapshot(snapshot)?;
+        }
+
+        self.save_undo_journal(&journal)?;
+        Ok(entry)
+    }
+
+    /// Current HEAD and local branch tips
+    fn ref_snapshot(&self) -> Result<RefSnapshot> {
+        let head_ref = self.repo.find_reference("HEAD")?;
+        let head = HeadState {
+            branch: head_ref.symbolic_target().map(String::from),
+            commit: head_ref.resolve().ok().and_then(|r| r.target()).map(|oid| oid.to_string()),
+        };
+
+        let mut branches = BTreeMap::new();
+        for reference in self.repo.references_glob("refs/heads/*")? {
+            let reference = reference?;
+            if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+                branches.insert(name.to_string(), target.to_string());
+            }
+        }
+
+        Ok(RefSnapshot { head, branches })
+    }
+
+    fn undo_journal_path(&self) -> PathBuf {
+        self.repo.path().join("caogit").join("undo.json")
+    }
+
+    fn load_undo_journal(&self) -> Result<Vec<UndoEntry>> {
+        let path = self.undo_journal_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path)
+            .context("Failed to read undo journal")?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_undo_journal(&self, journal: &[UndoEntry]) -> Result<()> {
+        let path = self.undo_journal_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(journal)?)
+            .context("Failed to write undo journal")?;
+        Ok(())
+    }
+}
+
+/// Branches whose tip differs between two snapshots
+fn ref_changes(before: &RefSnapshot, after: &RefSnapshot) -> Vec<RefChange> {
+    let mut names: Vec<&String> = before.branches.keys().chain(after.branches.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names.into_iter()
+        .filter_map(|name| {
+            let old = before.branches.get(name);
+            let new = after.branches.get(name);
+            (old != new).then(|| RefChange {
+                name: name.clone(),
+                before: old.cloned(),
+                after: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Rebuild the snapshot taken before a recorded entry from the state right after it
+fn snapshot_before_entry(entry: &UndoEntry, after: &RefSnapshot) -> RefSnapshot {
+    let mut branches = after.branches.clone();
+    for change in &entry.refs {
+        match &change.before {
+            Some(before) => branches.insert(change.name.clone(), before.clone()),
+            None => branches.remove(&change.name),
+        };
+    }
+    RefSnapshot { head: entry.head_before.clone(), branches }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use super::super::test_support::{self, commit_file};
+
+    fn snapshot(head: &str, branches: &[(&str, &str)]) -> RefSnapshot {
+        RefSnapshot {
+            head: HeadState { branch: Some("refs/heads/main".into()), commit: Some(head.into()) },
+            branches: branches.iter().map(|(name, oid)| (name.to_string(), oid.to_string())).collect(),
+        }
+    }
+
+    fn init_repo() -> (tempfile::TempDir, GitRepository) {
+        let (dir, repo) = test_support::init_repo();
+        commit_file(&repo, "f.txt", "one\n", "initial");
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_ref_changes() {
+        let before = snapshot("a", &[("refs/heads/main", "a"), ("refs/heads/old", "b")]);
+        let after = snapshot("c", &[("refs/heads/main", "c"), ("refs/heads/new", "d")]);
+        let changes = ref_changes(&before, &after);
+
+        let summary: Vec<(&str, Option<&str>, Option<&str>)> = changes.iter()
+            .map(|c| (c.name.as_str(), c.before.as_deref(), c.after.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("refs/heads/main", Some("a"), Some("c")),
+            ("refs/heads/new", None, Some("d")),
+            ("refs/heads/old", Some("b"), None),
+        ]);
+
+        let restored = snapshot_before_entry(&UndoEntry {
+            id: 0,
+            operation: "merge".into(),
+            description: String::new(),
+            timestamp: 0,
+            head_before: before.head.clone(),
+            head_after: after.head.clone(),
+            refs: changes,
+            in_progress: false,
+            snapshot: None,
+        }, &after);
+        assert_eq!(restored.branches, before.branches);
+        assert!(restored.head == before.head);
+    }
+
+    #[test]
+    fn test_undo_commit_keeps_changes_staged() {
+        let (dir, mut repo) = init_repo();
+        let initial = repo.repo.head().unwrap().target().unwrap();
+
+        fs::write(dir.path().join("f.txt"), "two\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.record_operation("commit", "Commit two", |r| r.commit("two")).unwrap();
+        // Operations that leave every reference alone are not recorded
+        repo.record_operation("commit", "Nothing", |_| Ok(())).unwrap();
+        assert_eq!(repo.get_undo_history().unwrap().len(), 1);
+
+        let entry = repo.undo_last_operation().unwrap();
+        assert_eq!(entry.description, "Commit two");
+        assert_eq!(repo.repo.head().unwrap().target().unwrap(), initial);
+        let staged = repo.repo.index().unwrap().get_path(Path::new("f.txt"), 0).unwrap();
+        assert_eq!(repo.repo.find_blob(staged.id).unwrap().content(), b"two\n");
+        assert!(repo.get_undo_history().unwrap().is_empty());
+        assert!(repo.undo_last_operation().is_err());
+    }
+
+    #[test]
+    fn test_undo_hard_reset_restores_discarded_changes() {
+        let (dir, mut repo) = init_repo();
+        fs::write(dir.path().join("f.txt"), "two\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("two").unwrap();
+        fs::write(dir.path().join("f.txt"), "dirty\n").unwrap();
+
+        repo.record_operation("reset", "Reset to HEAD~1", |r| r.reset("HEAD~1", "hard")).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("f.txt")).unwrap(), "one\n");
+        assert!(repo.get_undo_history().unwrap()[0].snapshot.is_some());
+
+        repo.undo_last_operation().unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("f.txt")).unwrap(), "dirty\n");
+        assert!(repo.reset_snapshots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_refuses_after_later_changes() {
+        let (dir, mut repo) = init_repo();
+        fs::write(dir.path().join("f.txt"), "two\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.record_operation("commit", "Commit two", |r| r.commit("two")).unwrap();
+
+        fs::write(dir.path().join("f.txt"), "three\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("three").unwrap();
+
+        assert!(repo.undo_last_operation().is_err());
+        assert_eq!(repo.get_undo_history().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,330 @@
+//! Worktree operations
+//!
+//! This module lists, adds, locks, prunes and removes linked worktrees, and
+//! tracks which branches are checked out in a worktree other than this one.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use git2::{
+    BranchType, Repository, StatusOptions, Worktree, WorktreeAddOptions, WorktreeLockStatus, WorktreePruneOptions,
+};
+
+use super::hooks::HookOptions;
+use super::repository::GitRepository;
+use super::types::WorktreeInfo;
+
+impl GitRepository {
+    /// List the main worktree followed by every linked worktree
+    pub fn list_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let mut worktrees = Vec::new();
+
+        if let Some(main) = self.main_repository()? {
+            if let Some(workdir) = main.workdir() {
+                let (branch, head) = head_state(&main);
+                worktrees.push(WorktreeInfo {
+                    name: workdir.file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    path: workdir.display().to_string(),
+                    branch,
+                    head,
+                    is_main: true,
+                    is_current: self.is_current_workdir(workdir),
+                    locked: false,
+                    lock_reason: None,
+                    prunable: false,
+                });
+            }
+        }
+
+        for name in self.repo.worktrees()?.iter().flatten() {
+            let worktree = self.repo.find_worktree(name)?;
+            worktrees.push(self.worktree_info(&worktree)?);
+        }
+
+        Ok(worktrees)
+    }
+
+    /// Add a linked worktree at `path`
+    ///
+    /// With `detach` HEAD is detached at `start_point`; otherwise `branch` is checked
+    /// out, after creating it at `start_point` when `create_branch` is set.
+    pub fn add_worktree(
+        &self,
+        path: &str,
+        branch: Option<&str>,
+        create_branch: bool,
+        start_point: Option<&str>,
+        detach: bool,
+        lock: bool,
+    ) -> Result<WorktreeInfo> {
+        let target = Path::new(path);
+        let name = target.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid worktree path: {}", path))?;
+        if self.repo.find_worktree(&name).is_ok() {
+            anyhow::bail!("A worktree named '{}' already exists", name);
+        }
+
+        let start_commit = self.repo.revparse_single(start_point.unwrap_or("HEAD"))
+            .context(format!("无法找到引用: {}", start_point.unwrap_or("HEAD")))?
+            .peel_to_commit()?;
+
+        // libgit2 always checks out a branch, so a detached worktree starts on a scratch one
+        let (branch_name, scratch) = if detach {
+            let base = format!("caogit-worktree-{}", name);
+            let free = |candidate: &String| self.repo.find_branch(candidate, BranchType::Local).is_err();
+            let branch = std::iter::once(base.clone())
+                .chain((2..).map(|i| format!("{}-{}", base, i)))
+                .find(free)
+                .unwrap_or(base);
+            (branch, true)
+        } else {
+            let branch = branch.ok_or_else(|| anyhow::anyhow!("A branch is required unless the worktree is detached"))?;
+            (branch.to_string(), false)
+        };
+
+        if create_branch || scratch {
+            self.repo.branch(&branch_name, &start_commit, false)
+                .context(format!("Failed to create branch '{}'", branch_name))?;
+        } else if let Some(other) = self.worktree_branches()?.get(&format!("refs/heads/{}", branch_name)) {
+            anyhow::bail!("Branch '{}' is already checked out at {}", branch_name, other.display());
+        }
+
+        let added = (|| -> Result<Worktree> {
+            let reference = self.repo.find_branch(&branch_name, BranchType::Local)
+                .context(format!("Branch '{}' not found", branch_name))?
+                .into_reference();
+            let mut opts = WorktreeAddOptions::new();
+            opts.lock(lock);
+            opts.reference(Some(&reference));
+
+            let worktree = self.repo.worktree(&name, target, Some(&opts))
+                .context(format!("Failed to add worktree at {}", path))?;
+            if scratch {
+                let detached = Repository::open_from_worktree(&worktree)
+                    .and_then(|linked| linked.set_head_detached(start_commit.id()));
+                if let Err(e) = detached {
+                    // Drop the half-made worktree so the scratch branch is no longer checked out
+                    let mut prune = WorktreePruneOptions::new();
+                    prune.valid(true).locked(true).working_tree(true);
+                    let _ = worktree.prune(Some(&mut prune));
+                    return Err(anyhow::Error::new(e).context(format!("Failed to detach worktree at {}", path)));
+                }
+            }
+            Ok(worktree)
+        })();
+
+        // The scratch branch only served the checkout; a branch created for a failed add goes too
+        if scratch || (create_branch && added.is_err()) {
+            let deleted = self.repo.find_branch(&branch_name, BranchType::Local)
+                .and_then(|mut branch| branch.delete());
+            if added.is_ok() {
+                deleted?;
+            }
+        }
+        let worktree = added?;
+
+        self.worktree_info(&worktree)
+    }
+
+    /// Lock a linked worktree so it is not pruned, with an optional reason
+    pub fn lock_worktree(&self, name: &str, reason: Option<&str>) -> Result<()> {
+        let worktree = self.find_linked_worktree(name)?;
+        worktree.lock(reason)
+            .context(format!("Failed to lock worktree '{}'", name))?;
+        Ok(())
+    }
+
+    /// Unlock a linked worktree
+    pub fn unlock_worktree(&self, name: &str) -> Result<()> {
+        let worktree = self.find_linked_worktree(name)?;
+        worktree.unlock()
+            .context(format!("Failed to unlock worktree '{}'", name))?;
+        Ok(())
+    }
+
+    /// Drop the administrative files of worktrees whose directory is gone
+    ///
+    /// Returns the names of the pruned worktrees; locked ones are kept.
+    pub fn prune_worktrees(&self) -> Result<Vec<String>> {
+        let mut pruned = Vec::new();
+        for name in self.repo.worktrees()?.iter().flatten() {
+            let worktree = self.repo.find_worktree(name)?;
+            if worktree.is_prunable(None)? {
+                worktree.prune(None)
+                    .context(format!("Failed to prune worktree '{}'", name))?;
+                pruned.push(name.to_string());
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Delete a linked worktree and its directory
+    ///
+    /// Refuses when it has local changes or untracked files unless `force` is set.
+    pub fn remove_worktree(&self, name: &str, force: bool) -> Result<()> {
+        let worktree = self.find_linked_worktree(name)?;
+        if let WorktreeLockStatus::Locked(_) = worktree.is_locked()? {
+            anyhow::bail!("Worktree '{}' is locked. Please unlock it first.", name);
+        }
+
+        if !force && worktree.validate().is_ok() {
+            let linked = Repository::open_from_worktree(&worktree)?;
+            let mut status_opts = StatusOptions::new();
+            status_opts.include_untracked(true).include_ignored(false);
+            if !linked.statuses(Some(&mut status_opts))?.is_empty() {
+                anyhow::bail!(
+                    "Cannot remove worktree '{}': it has uncommitted changes or untracked files.",
+                    name
+                );
+            }
+        }
+
+        let path = worktree.path().to_path_buf();
+        let mut opts = WorktreePruneOptions::new();
+        opts.valid(true).working_tree(true);
+        worktree.prune(Some(&mut opts))
+            .context(format!("Failed to remove worktree '{}'", name))?;
+
+        // Prune leaves the directory behind when the working tree could not be resolved
+        if path.exists() {
+            fs::remove_dir_all(&path)
+                .context(format!("Failed to delete {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Open a linked worktree as a repository of its own
+    pub fn open_worktree(&self, name: &str) -> Result<GitRepository> {
+        let worktree = self.find_linked_worktree(name)?;
+        worktree.validate()
+            .context(format!("Worktree '{}' is missing or invalid", name))?;
+        let repo = Repository::open_from_worktree(&worktree)?;
+        Ok(GitRepository { repo, hooks: HookOptions::default() })
+    }
+
+    /// Branches checked out in a worktree other than this one, by full reference name
+    pub(crate) fn worktree_branches(&self) -> Result<HashMap<String, PathBuf>> {
+        let mut branches = HashMap::new();
+
+        if let Some(main) = self.main_repository()? {
+            if let (Some(workdir), (Some(branch), _)) = (main.workdir(), head_state(&main)) {
+                if !self.is_current_workdir(workdir) {
+                    branches.insert(format!("refs/heads/{}", branch), workdir.to_path_buf());
+                }
+            }
+        }
+
+        for name in self.repo.worktrees()?.iter().flatten() {
+            let Ok(worktree) = self.repo.find_worktree(name) else { continue };
+            if worktree.validate().is_err() || self.is_current_workdir(worktree.path()) {
+                continue;
+            }
+            let Ok(linked) = Repository::open_from_worktree(&worktree) else { continue };
+            if let (Some(branch), _) = head_state(&linked) {
+                branches.insert(format!("refs/heads/{}", branch), worktree.path().to_path_buf());
+            }
+        }
+
+        Ok(branches)
+    }
+
+    fn worktree_info(&self, worktree: &Worktree) -> Result<WorktreeInfo> {
+        let (locked, lock_reason) = match worktree.is_locked()? {
+            WorktreeLockStatus::Locked(reason) => (true, reason),
+            WorktreeLockStatus::Unlocked => (false, None),
+        };
+        let (branch, head) = match Repository::open_from_worktree(worktree) {
+            Ok(linked) => head_state(&linked),
+            Err(_) => (None, None),
+        };
+
+        Ok(WorktreeInfo {
+            name: worktree.name().unwrap_or("").to_string(),
+            path: worktree.path().display().to_string(),
+            branch,
+            head,
+            is_main: false,
+            is_current: self.is_current_workdir(worktree.path()),
+            locked,
+            lock_reason: lock_reason.filter(|r| !r.is_empty()),
+            prunable: worktree.is_prunable(None).unwrap_or(false),
+        })
+    }
+
+    fn find_linked_worktree(&self, name: &str) -> Result<Worktree> {
+        self.repo.find_worktree(name)
+            .context(format!("Worktree '{}' not found", name))
+    }
+
+    /// The repository owning the main worktree, or `None` when it is bare
+    fn main_repository(&self) -> Result<Option<Repository>> {
+        // A linked worktree's git dir points at the shared one through its `commondir` file
+        let gitdir = self.repo.path();
+        let common = match fs::read_to_string(gitdir.join("commondir")) {
+            Ok(relative) => gitdir.join(relative.trim()),
+            Err(_) => gitdir.to_path_buf(),
+        };
+        let main = Repository::open(&common)?;
+        Ok((!main.is_bare()).then_some(main))
+    }
+
+    fn is_current_workdir(&self, path: &Path) -> bool {
+        match (self.repo.workdir(), fs::canonicalize(path)) {
+            (Some(current), Ok(path)) => fs::canonicalize(current).is_ok_and(|c| c == path),
+            _ => false,
+        }
+    }
+}
+
+/// Branch short name (if attached) and commit of a repository's HEAD
+fn head_state(repo: &Repository) -> (Option<String>, Option<String>) {
+    let Ok(head) = repo.find_reference("HEAD") else { return (None, None) };
+    let branch = head.symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .map(String::from);
+    let commit = head.resolve().ok()
+        .and_then(|r| r.target())
+        .map(|oid| oid.to_string());
+    (branch, commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::{commit_file, init_repo};
+
+    fn branch_names(repo: &GitRepository) -> Vec<String> {
+        repo.repo.branches(Some(BranchType::Local)).unwrap()
+            .map(|b| b.unwrap().0.name().unwrap().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_detached_worktree_cleans_up_scratch_branch() {
+        let (_dir, repo) = init_repo();
+        let head = commit_file(&repo, "f.txt", "one\n", "initial");
+        let commit = repo.repo.head().unwrap().peel_to_commit().unwrap();
+        repo.repo.branch("caogit-worktree-wt", &commit, false).unwrap();
+        let before = branch_names(&repo);
+
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("wt");
+        let info = repo.add_worktree(target.to_str().unwrap(), None, false, None, true, false).unwrap();
+        assert_eq!(info.branch, None);
+        assert_eq!(info.head.as_deref(), Some(head.as_str()));
+        assert_eq!(branch_names(&repo), before);
+
+        // A failed add leaves neither a scratch branch nor a newly created one behind
+        let blocked = outside.path().join("blocked");
+        fs::write(&blocked, "not a directory").unwrap();
+        assert!(repo.add_worktree(blocked.to_str().unwrap(), None, false, None, true, false).is_err());
+        assert!(repo.add_worktree(blocked.to_str().unwrap(), Some("topic"), true, None, false, false).is_err());
+        assert_eq!(branch_names(&repo), before);
+    }
+}
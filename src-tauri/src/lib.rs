@@ -15,6 +15,11 @@ use appstore_update::*;
 
 use tauri::Emitter;
 
+/// Answer a `git credential` request from the keychain, for `caogit credential <get|store|erase>`
+pub fn run_credential_helper(action: &str) -> i32 {
+    git_ops::run_keychain_helper(action)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -63,8 +68,19 @@ pub fn run() {
             stage_file,
             unstage_file,
             discard_file,
+            stage_hunk,
+            unstage_hunk,
+            discard_hunk,
+            stage_lines,
+            unstage_lines,
+            discard_lines,
             commit_changes,
+            amend_commit,
+            create_fixup_commit,
             get_commits,
+            query_commits,
+            get_commit_details,
+            verify_commit_signature,
             get_branches,
             create_branch,
             checkout_branch,
@@ -86,15 +102,70 @@ pub fn run() {
             get_tags,
             delete_tag,
             get_file_diff,
+            diff_revisions,
             clone_repository,
+            cancel_clone,
             init_repository,
             detect_project_type,
             get_file_blame,
             cherry_pick,
             cherry_pick_batch,
+            revert_commit,
+            revert_batch,
+            revert_continue,
+            revert_abort,
             get_conflicts,
             resolve_conflict,
             abort_merge,
+            get_rebase_plan,
+            rebase_interactive,
+            rebase_continue,
+            rebase_skip,
+            rebase_abort,
+            get_rebase_status,
+            reword_commit,
+            rebase_autosquash,
+            format_patch,
+            apply_patch,
+            apply_mailbox,
+            reset_to_commit,
+            undo_hard_reset,
+            reset_paths,
+            get_reflog_refs,
+            get_reflog,
+            get_undo_history,
+            undo_last_operation,
+            find_lost_commits,
+            recover_branch,
+            bisect_start,
+            bisect_mark,
+            get_bisect_status,
+            bisect_reset,
+            bisect_run,
+            list_worktrees,
+            add_worktree,
+            lock_worktree,
+            unlock_worktree,
+            prune_worktrees,
+            remove_worktree,
+            open_worktree,
+            list_submodules,
+            init_submodules,
+            update_submodules,
+            sync_submodules,
+            get_submodule_diff,
+            get_lfs_info,
+            get_lfs_files,
+            lfs_pull,
+            lfs_locks,
+            lfs_lock,
+            lfs_unlock,
+            get_sparse_checkout,
+            set_sparse_checkout,
+            add_sparse_directories,
+            remove_sparse_directories,
+            reapply_sparse_checkout,
+            disable_sparse_checkout,
             set_window_theme,
             call_ai_api,
             copy_to_clipboard,
@@ -116,6 +187,8 @@ pub fn run() {
             keychain_delete,
             keychain_exists,
             keychain_migrate,
+            get_credential_helpers,
+            set_keychain_credential_helper,
             // 条件编译：自动更新功能（仅 DMG 版本）
             #[cfg(feature = "auto-update")]
             install_update,
@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // git 以 `caogit credential <get|store|erase>` 调用时作为凭据 helper 运行，不启动窗口
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "credential" {
+        std::process::exit(tauri_app_caogit_lib::run_credential_helper(&args[2]));
+    }
+
     tauri_app_caogit_lib::run()
 }
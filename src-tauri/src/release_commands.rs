@@ -1,4 +1,4 @@
-use crate::git_ops::GitRepository;
+use crate::git_ops::{AuthConfig, GitRepository};
 use crate::github_api::{GitHubClient, GitHubRelease, WorkflowRun};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -80,10 +80,18 @@ pub async fn get_release_info(
 pub async fn publish_new_release(
     repo_path: String,
     config: PublishConfig,
-    _github_token: Option<String>,
+    github_token: Option<String>,
 ) -> Result<String, String> {
     let repo = GitRepository::open(&repo_path).map_err(|e| e.to_string())?;
 
+    // HTTPS 推送使用 GitHub token，未提供时由凭据提供者尝试其他来源
+    let auth_config = github_token.filter(|token| !token.is_empty()).map(|token| AuthConfig {
+        auth_type: "token".to_string(),
+        token: Some(token),
+        username: None,
+        password: None,
+    });
+
     // 第一步：更新所有版本文件
     // 注意：这会修改文件，所以状态检查必须在更新之前完成（但我们允许自动生成的改动）
     update_tauri_config_version(&repo_path, &config.version)
@@ -150,7 +158,7 @@ pub async fn publish_new_release(
 
         // 先推送提交
         println!("正在推送提交到 origin/{}...", current_branch);
-        repo.push("origin", &current_branch, None)
+        repo.push("origin", &current_branch, auth_config.clone())
             .map_err(|e| {
                 eprintln!("推送提交失败: {}", e);
                 format!("推送提交失败: {}。请确保已配置 Git 认证（SSH 密钥或凭据管理器）", e)
@@ -159,7 +167,7 @@ pub async fn publish_new_release(
 
         // 再推送标签到远程
         println!("正在推送标签 {} 到 origin...", config.version);
-        repo.push_tag("origin", &config.version)
+        repo.push_tag("origin", &config.version, auth_config)
             .map_err(|e| {
                 eprintln!("推送标签失败: {}", e);
                 format!("推送标签失败: {}。请确保已配置 Git 认证（SSH 密钥或凭据管理器）", e)